@@ -327,6 +327,22 @@ impl<'a> TypedEvent<'a> {
         self.record.EventHeader.EventDescriptor.Version
     }
 
+    /// Use the `keyword` function to obtain the Keyword bitmask of the [EventRecord]
+    ///
+    /// This getter returns the keywords of the ETW Event that triggered the registered callback.
+    /// The meaning of the bits depends on the provider.
+    ///
+    /// # Example
+    /// ```rust
+    /// let my_callback = |record: EventRecord, schema_locator: &mut SchemaLocator| {
+    ///     let schema = schema_locator.event_schema(record)?;
+    ///     let keyword = schema.keyword();
+    /// };
+    /// ```
+    pub fn keyword(&self) -> u64 {
+        self.record.EventHeader.EventDescriptor.Keyword
+    }
+
     /// Use the `process_id` function to obtain the ProcessId of the [EventRecord]
     ///
     /// This getter returns the ProcessId of the process that triggered the ETW Event
@@ -62,15 +62,16 @@ pub fn parse_unk_size_null_ansi_vec(v: &[u8]) -> Vec<u8> {
         .collect::<Vec<u8>>()
 }
 
+/// Decodes a UTF-16 string which ends at the first null character or at the end of
+/// the buffer. Unpaired surrogates are replaced with U+FFFD rather than failing, and a
+/// trailing odd byte is ignored.
 pub fn parse_null_utf16_string(v: &[u8]) -> String {
-    String::from_utf16_lossy(
-        v.chunks_exact(2)
-            .map(|a| u16::from_ne_bytes([a[0], a[1]]))
-            .collect::<Vec<u16>>()
-            .as_slice(),
-    )
-    .trim_matches(char::default())
-    .to_string()
+    let units: Vec<u16> = v
+        .chunks_exact(2)
+        .map(|a| u16::from_ne_bytes([a[0], a[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
 }
 
 pub fn parse_utf16_guid(v: &[u8]) -> String {
@@ -85,3 +86,36 @@ pub fn parse_utf16_guid(v: &[u8]) -> String {
     .trim_matches('}')
     .to_string()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utf16_bytes(units: &[u16]) -> Vec<u8> {
+        units.iter().flat_map(|unit| unit.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    fn test_parse_null_utf16_string() {
+        let signature = "instance void  (class System.Collections.Generic.List`1<!!0>)";
+        let mut units: Vec<u16> = signature.encode_utf16().collect();
+        units.push(0);
+        // Anything after the null terminator isn't part of the string.
+        units.extend("garbage".encode_utf16());
+        assert_eq!(parse_null_utf16_string(&utf16_bytes(&units)), signature);
+
+        // Surrogate pairs are decoded, unpaired surrogates are replaced.
+        let mut units: Vec<u16> = "Foo`1<".encode_utf16().collect();
+        units.extend([0xd83d, 0xde00, 0xd800, u16::from(b'>'), 0xdc00]);
+        assert_eq!(
+            parse_null_utf16_string(&utf16_bytes(&units)),
+            "Foo`1<\u{1f600}\u{fffd}>\u{fffd}"
+        );
+
+        // No terminator, and an odd trailing byte.
+        let mut bytes = utf16_bytes(&"Bar".encode_utf16().collect::<Vec<u16>>());
+        bytes.push(b'x');
+        assert_eq!(parse_null_utf16_string(&bytes), "Bar");
+        assert_eq!(parse_null_utf16_string(&[]), "");
+    }
+}
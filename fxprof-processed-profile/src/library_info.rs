@@ -85,10 +85,32 @@ pub struct SymbolTable {
 
 impl SymbolTable {
     /// Create a [`SymbolTable`] from a list of [`Symbol`]s.
-    pub fn new(mut symbols: Vec<Symbol>) -> Self {
+    ///
+    /// The symbols don't need to be sorted. If multiple symbols have the same address,
+    /// only the first one is kept. If a symbol's size makes it overlap the next symbol,
+    /// its size is clamped so that it ends where the next symbol starts.
+    pub fn new(symbols: Vec<Symbol>) -> Self {
+        Self::new_with_clamped_count(symbols).0
+    }
+
+    /// Like [`SymbolTable::new`], but also returns the number of symbols whose size was
+    /// clamped because they overlapped the next symbol. Overlaps usually mean that the
+    /// symbol information is broken, so this is worth reporting if it's not zero.
+    pub fn new_with_clamped_count(mut symbols: Vec<Symbol>) -> (Self, usize) {
         symbols.sort();
         symbols.dedup_by_key(|symbol| symbol.address);
-        Self { symbols }
+        let mut clamped_count = 0;
+        for i in 1..symbols.len() {
+            let next_address = symbols[i].address;
+            let symbol = &mut symbols[i - 1];
+            if let Some(size) = symbol.size.as_mut() {
+                if symbol.address.saturating_add(*size) > next_address {
+                    *size = next_address - symbol.address;
+                    clamped_count += 1;
+                }
+            }
+        }
+        (Self { symbols }, clamped_count)
     }
 
     /// Look up the symbol for an address. This address is relative to the library's base address.
@@ -120,3 +142,44 @@ pub struct Symbol {
     /// The symbol name.
     pub name: String,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn symbol(address: u32, size: u32, name: &str) -> Symbol {
+        Symbol {
+            address,
+            size: Some(size),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_symbol_table_unsorted_and_overlapping() {
+        let (table, clamped_count) = SymbolTable::new_with_clamped_count(vec![
+            symbol(0x300, 0x10, "c"),
+            symbol(0x100, 0x180, "a"), // overlaps "b"
+            symbol(0x200, 0x20, "b"),
+        ]);
+        assert_eq!(table.lookup(0x0ff), None);
+        assert_eq!(table.lookup(0x100).unwrap().name, "a");
+        assert_eq!(table.lookup(0x1ff).unwrap().name, "a");
+        assert_eq!(table.lookup(0x200).unwrap().name, "b");
+        assert_eq!(table.lookup(0x21f).unwrap().name, "b");
+        assert_eq!(table.lookup(0x220), None);
+        assert_eq!(table.lookup(0x250), None);
+        assert_eq!(table.lookup(0x305).unwrap().name, "c");
+        assert_eq!(table.lookup(0x100).unwrap().size, Some(0x100));
+        assert_eq!(clamped_count, 1);
+        // The count isn't part of the table.
+        assert_eq!(
+            table,
+            SymbolTable::new(vec![
+                symbol(0x100, 0x100, "a"),
+                symbol(0x200, 0x20, "b"),
+                symbol(0x300, 0x10, "c"),
+            ])
+        );
+    }
+}
@@ -10,7 +10,7 @@ use crate::category_color::CategoryColor;
 use crate::counters::{Counter, CounterHandle};
 use crate::cpu_delta::CpuDelta;
 use crate::fast_hash_map::FastHashMap;
-use crate::frame::{Frame, FrameInfo};
+use crate::frame::{Frame, FrameFlags, FrameInfo};
 use crate::frame_table::{InternalFrame, InternalFrameLocation};
 use crate::global_lib_table::{GlobalLibTable, LibraryHandle, UsedLibraryAddressesIterator};
 use crate::lib_mappings::LibMappings;
@@ -404,6 +404,16 @@ impl Profile {
         StringHandle(self.string_table.index_for_string(s))
     }
 
+    /// Create a [`FrameInfo`] for a label-only frame, such as a synthetic "GC" or
+    /// "JIT" group node. This interns the label string for you.
+    pub fn label_frame(&mut self, label: &str, category_pair: CategoryPairHandle) -> FrameInfo {
+        FrameInfo {
+            frame: Frame::Label(self.intern_string(label)),
+            category_pair,
+            flags: FrameFlags::empty(),
+        }
+    }
+
     /// Get the string for a string handle. This is sometimes useful when writing tests.
     ///
     /// Panics if the handle wasn't found, which can happen if you pass a handle
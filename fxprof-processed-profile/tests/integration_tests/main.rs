@@ -1586,3 +1586,53 @@ fn profile_counters_with_sorted_processes() {
         )
     )
 }
+
+#[test]
+fn profile_with_label_frames() {
+    let mut profile = Profile::new(
+        "test",
+        ReferenceTimestamp::from_millis_since_unix_epoch(1636162232627.0),
+        SamplingInterval::from_millis(1),
+    );
+    let process = profile.add_process("test", 123, Timestamp::from_millis_since_reference(0.0));
+    let thread = profile.add_thread(
+        process,
+        12345,
+        Timestamp::from_millis_since_reference(0.0),
+        true,
+    );
+    let gc = profile.label_frame("GC", CategoryHandle::OTHER.into());
+    let jit = profile.label_frame("JIT", CategoryHandle::OTHER.into());
+    profile.add_sample(
+        thread,
+        Timestamp::from_millis_since_reference(1.0),
+        vec![gc.clone(), jit].into_iter(),
+        CpuDelta::ZERO,
+        1,
+    );
+    profile.add_sample(
+        thread,
+        Timestamp::from_millis_since_reference(2.0),
+        vec![gc].into_iter(),
+        CpuDelta::ZERO,
+        1,
+    );
+
+    let json = serde_json::to_value(&profile).unwrap();
+    let thread = &json["threads"][0];
+    assert_eq!(thread["frameTable"]["length"], 2);
+    assert_eq!(thread["frameTable"]["address"], json!([-1, -1]));
+    assert_eq!(thread["frameTable"]["nativeSymbol"], json!([null, null]));
+    let func_names: Vec<&str> = thread["frameTable"]["func"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|func| {
+            let name = &thread["funcTable"]["name"][func.as_u64().unwrap() as usize];
+            thread["stringArray"][name.as_u64().unwrap() as usize]
+                .as_str()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(func_names, ["GC", "JIT"]);
+}
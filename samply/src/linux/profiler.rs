@@ -115,6 +115,7 @@ pub fn start_recording(
     let initial_exec_name_and_cmdline = (initial_exec_name, initial_cmdline);
     let observer_thread = thread::spawn(move || {
         let unstable_presymbolicate = profile_creation_props.unstable_presymbolicate;
+        let presymbolicate_timeout = profile_creation_props.presymbolicate_timeout;
         let mut converter = make_converter(interval, profile_creation_props);
 
         // Wait for the initial pid to profile.
@@ -147,6 +148,7 @@ pub fn start_recording(
             profile_another_pid_reply_sender,
             stop_receiver,
             unstable_presymbolicate,
+            presymbolicate_timeout,
             Some(initial_exec_name_and_cmdline),
         );
     });
@@ -287,6 +289,7 @@ fn start_profiling_pid(
             let interval = recording_props.interval;
             let time_limit = recording_props.time_limit;
             let unstable_presymbolicate = profile_creation_props.unstable_presymbolicate;
+            let presymbolicate_timeout = profile_creation_props.presymbolicate_timeout;
             let mut converter = make_converter(interval, profile_creation_props);
             let SamplerRequest::StartProfilingAnotherProcess(pid, attach_mode) =
                 profile_another_pid_request_receiver.recv().unwrap()
@@ -308,6 +311,7 @@ fn start_profiling_pid(
                 profile_another_pid_reply_sender,
                 ctrl_c_receiver,
                 unstable_presymbolicate,
+                presymbolicate_timeout,
                 None,
             )
         }
@@ -584,6 +588,7 @@ fn run_profiler(
     more_processes_reply_sender: Sender<bool>,
     mut stop_receiver: oneshot::Receiver<()>,
     unstable_presymbolicate: bool,
+    presymbolicate_timeout: Duration,
     mut initial_exec_name_and_cmdline: Option<(String, Vec<String>)>,
 ) {
     // eprintln!("Running...");
@@ -736,6 +741,7 @@ fn run_profiler(
         crate::shared::symbol_precog::presymbolicate(
             &profile,
             &output_filename.with_extension("syms.json"),
+            presymbolicate_timeout,
         );
     }
 }
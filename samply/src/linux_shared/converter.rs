@@ -157,12 +157,19 @@ where
             .add_category("JIT app cache", CategoryColor::Green)
             .into();
         let allow_jit_function_recycling = profile_creation_props.reuse_threads;
-        let simpleperf_jit_app_cache_library = SyntheticJitLibrary::new(
+        // The app cache library is shared by all processes, so it has no pid of its own.
+        let mut simpleperf_jit_app_cache_library = SyntheticJitLibrary::new(
             "JIT app cache".to_string(),
+            0,
+            first_sample_time,
             simpleperf_jit_category,
             &mut profile,
             allow_jit_function_recycling,
+            profile_creation_props.coalesce_jit_symbols,
         );
+        simpleperf_jit_app_cache_library.set_max_symbols(profile_creation_props.max_jit_symbols);
+        let jit_base_address = profile_creation_props.jit_base_address;
+        simpleperf_jit_app_cache_library.set_base_relative_address(jit_base_address);
         if let Some(simpleperf_symbol_tables) = simpleperf_symbol_tables {
             let dex_category: CategoryPairHandle =
                 profile.add_category("DEX", CategoryColor::Green).into();
@@ -206,7 +213,13 @@ where
                         name: demangle_any(&s.name),
                     })
                     .collect();
-                let symbol_table = SymbolTable::new(symbols);
+                let (symbol_table, clamped_count) = SymbolTable::new_with_clamped_count(symbols);
+                if clamped_count != 0 {
+                    log::warn!(
+                        "{clamped_count} overlapping symbols in the simpleperf symbol table for {}",
+                        f.path
+                    );
+                }
                 let symbol_table = SymbolTableFromSimpleperf {
                     file_offset_of_min_vaddr_in_elf_file,
                     min_vaddr,
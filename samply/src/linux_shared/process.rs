@@ -335,7 +335,9 @@ where
         size: u32,
         info: LibMappingInfo,
     ) {
-        let relative_address = jit_lib.add_function(name, size);
+        let Some(relative_address) = jit_lib.add_function(name, size) else {
+            return;
+        };
 
         self.jit_app_cache_mapping_ops.push(
             timestamp_raw,
@@ -80,6 +80,7 @@ pub fn start_recording(
     };
 
     let unstable_presymbolicate = profile_creation_props.unstable_presymbolicate;
+    let presymbolicate_timeout = profile_creation_props.presymbolicate_timeout;
 
     let (task_sender, task_receiver) = unbounded();
 
@@ -216,6 +217,7 @@ pub fn start_recording(
         crate::shared::symbol_precog::presymbolicate(
             &profile,
             &output_file.with_extension("syms.json"),
+            presymbolicate_timeout,
         );
     }
 
@@ -33,6 +33,10 @@ pub use mac::{kernel_error, thread_act, thread_info};
 use profile_json_preparse::parse_libinfo_map_from_profile_file;
 use server::{start_server_main, PortSelection, ServerProps};
 use shared::included_processes::IncludedProcesses;
+#[cfg(target_os = "windows")]
+use shared::recording_props::CoreClrAllocSampling;
+#[cfg(target_os = "windows")]
+use shared::recording_props::CoreClrThreadFilter;
 use shared::recording_props::{
     CoreClrProfileProps, ProcessLaunchProps, ProfileCreationProps, RecordingMode, RecordingProps,
 };
@@ -188,6 +192,54 @@ fn parse_time_range(
     Ok((start, if is_duration { start + end } else { end }))
 }
 
+#[allow(unused)]
+fn parse_provider(arg: &str) -> Result<(String, u64, u8), String> {
+    let mut parts = arg.splitn(3, ':');
+    let name = parts.next().unwrap_or_default();
+    if name.is_empty() {
+        return Err("missing provider name".to_string());
+    }
+    let keywords = match parts.next() {
+        Some(keywords) => {
+            let hex = keywords
+                .strip_prefix("0x")
+                .or_else(|| keywords.strip_prefix("0X"));
+            match hex {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => keywords.parse(),
+            }
+            .map_err(|e| format!("invalid keywords {keywords:?}: {e}"))?
+        }
+        None => u64::MAX,
+    };
+    let level = match parts.next() {
+        Some(level) => level
+            .parse()
+            .map_err(|e| format!("invalid level {level:?}: {e}"))?,
+        None => 5,
+    };
+    Ok((name.to_string(), keywords, level))
+}
+
+/// Parses a relative address, in hex with a "0x" prefix or in decimal.
+fn parse_relative_address(arg: &str) -> Result<u32, String> {
+    let hex = arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X"));
+    match hex {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => arg.parse(),
+    }
+    .map_err(|e| format!("invalid address {arg:?}: {e}"))
+}
+
+/// Parses a non-negative number of seconds, which may have a fractional part.
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let seconds: f64 = arg
+        .parse()
+        .map_err(|e| format!("invalid number {arg:?}: {e}"))?;
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| format!("{arg:?} is not a valid non-negative number of seconds"))
+}
+
 #[allow(unused)]
 #[derive(Debug, Args)]
 struct RecordArgs {
@@ -241,6 +293,12 @@ struct RecordArgs {
     #[clap(long, require_equals = true, value_name = "FLAG", value_enum, value_delimiter = ',', num_args = 0.., default_missing_value = "enabled")]
     coreclr: Vec<CoreClrArgs>,
 
+    /// Enable an additional ETW provider, in the form "Name[:keywords[:level]]" (can be specified multiple times; Windows only).
+    /// Keywords are merged with the ones samply enables for the same provider.
+    #[cfg(target_os = "windows")]
+    #[arg(long, value_name = "PROVIDER", value_parser = parse_provider)]
+    coreclr_provider: Vec<(String, u64, u8)>,
+
     /// VM hack for arm64 Windows VMs to not try to record PROFILE events (Windows only).
     #[cfg(target_os = "windows")]
     #[arg(long)]
@@ -270,7 +328,21 @@ enum CoreClrArgs {
     #[cfg(target_os = "windows")]
     GcDetailedAllocs,
     #[cfg(target_os = "windows")]
+    GcDetailedAllocsHigh,
+    #[cfg(target_os = "windows")]
     EventStacks,
+    #[cfg(target_os = "windows")]
+    GcCategories,
+    #[cfg(target_os = "windows")]
+    R2rMarkers,
+    #[cfg(target_os = "windows")]
+    GcHandles,
+    #[cfg(target_os = "windows")]
+    NoRundownMarkers,
+    #[cfg(target_os = "windows")]
+    TypeNames,
+    #[cfg(target_os = "windows")]
+    EndRundown,
 }
 
 impl std::fmt::Display for CoreClrArgs {
@@ -360,6 +432,24 @@ pub struct ProfileCreationArgs {
     #[arg(long)]
     fold_recursive_prefix: bool,
 
+    /// Merge adjacent JIT symbols which have the same name, e.g. for methods
+    /// which were loaded multiple times. This makes profiles smaller but
+    /// loses the distinction between the individual loads.
+    #[arg(long)]
+    coalesce_jit_symbols: bool,
+
+    /// Stop adding symbols to a JIT library once it has this many, and warn.
+    /// Protects against running out of memory on traces with a huge number of
+    /// JIT functions.
+    #[arg(long, value_name = "COUNT")]
+    max_jit_symbols: Option<usize>,
+
+    /// Lay out the symbols of JIT libraries from this relative address instead
+    /// of 0, e.g. "0x10000000". The relative addresses of JIT functions don't
+    /// depend on where the functions are in memory.
+    #[arg(long, value_name = "ADDRESS", default_value = "0", value_parser = parse_relative_address)]
+    jit_base_address: u32,
+
     /// If a process produces jitdump or marker files, unlink them after
     /// opening. This ensures that the files will not be left in /tmp,
     /// but it will also be impossible to look at JIT disassembly, and line
@@ -386,10 +476,40 @@ pub struct ProfileCreationArgs {
     #[arg(long)]
     unstable_presymbolicate: bool,
 
-    /// Emit markers for any unknown ETW events that are encountered.
+    /// How long to wait for the symbols of a single library when presymbolicating,
+    /// in seconds. Libraries whose symbols take longer (for example because the
+    /// symbol server is slow or unreachable) are skipped.
+    #[arg(long, value_name = "SECONDS", default_value = "60", value_parser = parse_seconds)]
+    unstable_presymbolicate_timeout: Duration,
+
+    /// Emit markers for any unknown ETW events that are encountered. This includes
+    /// the CoreCLR events that samply decodes but has no dedicated marker for, such
+    /// as GC heap statistics or non-verbose method events.
     #[cfg(target_os = "windows")]
     #[arg(long)]
     unknown_event_markers: bool,
+
+    /// Only create CoreCLR markers for the thread with this thread ID (can be specified
+    /// multiple times; Windows only). JIT symbols are still collected for all threads.
+    #[cfg(target_os = "windows")]
+    #[arg(long, value_name = "TID")]
+    coreclr_thread: Vec<u32>,
+
+    /// Don't create CoreCLR markers for the thread with this thread ID (can be specified
+    /// multiple times; Windows only).
+    #[cfg(target_os = "windows")]
+    #[arg(long, value_name = "TID")]
+    coreclr_exclude_thread: Vec<u32>,
+}
+
+impl ProfileCreationArgs {
+    #[cfg(target_os = "windows")]
+    fn coreclr_thread_filter(&self) -> CoreClrThreadFilter {
+        CoreClrThreadFilter {
+            include: self.coreclr_thread.clone(),
+            exclude: self.coreclr_exclude_thread.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Args)]
@@ -536,17 +656,25 @@ impl ImportArgs {
             main_thread_only: self.profile_creation_args.main_thread_only,
             reuse_threads: self.profile_creation_args.reuse_threads,
             fold_recursive_prefix: self.profile_creation_args.fold_recursive_prefix,
+            coalesce_jit_symbols: self.profile_creation_args.coalesce_jit_symbols,
+            max_jit_symbols: self.profile_creation_args.max_jit_symbols,
+            jit_base_address: self.profile_creation_args.jit_base_address,
             unlink_aux_files: self.profile_creation_args.unlink_aux_files,
             create_per_cpu_threads: self.profile_creation_args.per_cpu_threads,
             arg_count_to_include_in_process_name: self.profile_creation_args.include_args,
             override_arch: self.override_arch.clone(),
             unstable_presymbolicate: self.profile_creation_args.unstable_presymbolicate,
-            coreclr: to_coreclr_profile_props(&self.coreclr),
+            presymbolicate_timeout: self.profile_creation_args.unstable_presymbolicate_timeout,
+            coreclr: to_coreclr_profile_props(&self.coreclr, &[]),
             #[cfg(target_os = "windows")]
             unknown_event_markers: self.profile_creation_args.unknown_event_markers,
             #[cfg(not(target_os = "windows"))]
             unknown_event_markers: false,
             #[cfg(target_os = "windows")]
+            coreclr_thread_filter: self.profile_creation_args.coreclr_thread_filter(),
+            #[cfg(not(target_os = "windows"))]
+            coreclr_thread_filter: Default::default(),
+            #[cfg(target_os = "windows")]
             time_range: self.time_range,
             #[cfg(not(target_os = "windows"))]
             time_range: None,
@@ -659,16 +787,27 @@ impl RecordArgs {
             main_thread_only: self.profile_creation_args.main_thread_only,
             reuse_threads: self.profile_creation_args.reuse_threads,
             fold_recursive_prefix: self.profile_creation_args.fold_recursive_prefix,
+            coalesce_jit_symbols: self.profile_creation_args.coalesce_jit_symbols,
+            max_jit_symbols: self.profile_creation_args.max_jit_symbols,
+            jit_base_address: self.profile_creation_args.jit_base_address,
             unlink_aux_files: self.profile_creation_args.unlink_aux_files,
             create_per_cpu_threads: self.profile_creation_args.per_cpu_threads,
             arg_count_to_include_in_process_name: self.profile_creation_args.include_args,
             override_arch: None,
             unstable_presymbolicate: self.profile_creation_args.unstable_presymbolicate,
-            coreclr: to_coreclr_profile_props(&self.coreclr),
+            presymbolicate_timeout: self.profile_creation_args.unstable_presymbolicate_timeout,
+            #[cfg(target_os = "windows")]
+            coreclr: to_coreclr_profile_props(&self.coreclr, &self.coreclr_provider),
+            #[cfg(not(target_os = "windows"))]
+            coreclr: to_coreclr_profile_props(&self.coreclr, &[]),
             #[cfg(target_os = "windows")]
             unknown_event_markers: self.profile_creation_args.unknown_event_markers,
             #[cfg(not(target_os = "windows"))]
             unknown_event_markers: false,
+            #[cfg(target_os = "windows")]
+            coreclr_thread_filter: self.profile_creation_args.coreclr_thread_filter(),
+            #[cfg(not(target_os = "windows"))]
+            coreclr_thread_filter: Default::default(),
             time_range: None,
         }
     }
@@ -723,7 +862,10 @@ impl SymbolArgs {
     }
 }
 
-fn to_coreclr_profile_props(coreclr_args: &[CoreClrArgs]) -> CoreClrProfileProps {
+fn to_coreclr_profile_props(
+    coreclr_args: &[CoreClrArgs],
+    extra_providers: &[(String, u64, u8)],
+) -> CoreClrProfileProps {
     // on Windows, the ..Default::default() has no effect, and clippy doesn't like it
     #[allow(clippy::needless_update)]
     CoreClrProfileProps {
@@ -733,9 +875,32 @@ fn to_coreclr_profile_props(coreclr_args: &[CoreClrArgs]) -> CoreClrProfileProps
         #[cfg(target_os = "windows")]
         gc_suspensions: coreclr_args.contains(&CoreClrArgs::GcSuspendedThreads),
         #[cfg(target_os = "windows")]
-        gc_detailed_allocs: coreclr_args.contains(&CoreClrArgs::GcDetailedAllocs),
+        gc_detailed_allocs: coreclr_args.contains(&CoreClrArgs::GcDetailedAllocs)
+            || coreclr_args.contains(&CoreClrArgs::GcDetailedAllocsHigh),
+        #[cfg(target_os = "windows")]
+        gc_alloc_sampling: match (
+            coreclr_args.contains(&CoreClrArgs::GcDetailedAllocs),
+            coreclr_args.contains(&CoreClrArgs::GcDetailedAllocsHigh),
+        ) {
+            (true, true) => CoreClrAllocSampling::Both,
+            (false, true) => CoreClrAllocSampling::High,
+            _ => CoreClrAllocSampling::Low,
+        },
         #[cfg(target_os = "windows")]
         event_stacks: coreclr_args.contains(&CoreClrArgs::EventStacks),
+        #[cfg(target_os = "windows")]
+        gc_categories: coreclr_args.contains(&CoreClrArgs::GcCategories),
+        #[cfg(target_os = "windows")]
+        r2r_markers: coreclr_args.contains(&CoreClrArgs::R2rMarkers),
+        #[cfg(target_os = "windows")]
+        gc_handles: coreclr_args.contains(&CoreClrArgs::GcHandles),
+        #[cfg(target_os = "windows")]
+        suppress_rundown_markers: coreclr_args.contains(&CoreClrArgs::NoRundownMarkers),
+        #[cfg(target_os = "windows")]
+        resolve_type_names: coreclr_args.contains(&CoreClrArgs::TypeNames),
+        #[cfg(target_os = "windows")]
+        end_rundown: coreclr_args.contains(&CoreClrArgs::EndRundown),
+        extra_providers: extra_providers.to_vec(),
         ..Default::default()
     }
 }
@@ -854,4 +1019,37 @@ mod test {
         let opt_res = Opt::try_parse_from(["samply", "record", "-p", "1234", "rustup"]);
         assert!(opt_res.is_err());
     }
+
+    #[test]
+    fn verify_parse_provider() {
+        assert_eq!(
+            parse_provider("Microsoft-Windows-DotNETRuntime:0x2000:4"),
+            Ok(("Microsoft-Windows-DotNETRuntime".to_string(), 0x2000, 4))
+        );
+        assert_eq!(
+            parse_provider("MyEventSource"),
+            Ok(("MyEventSource".to_string(), u64::MAX, 5))
+        );
+        assert!(parse_provider("MyEventSource:zzz").is_err());
+        assert!(parse_provider(":0x1:4").is_err());
+    }
+
+    #[test]
+    fn verify_parse_relative_address() {
+        assert_eq!(parse_relative_address("0x10000000"), Ok(0x1000_0000));
+        assert_eq!(parse_relative_address("4096"), Ok(4096));
+        assert!(parse_relative_address("0x100000000").is_err());
+        assert!(parse_relative_address("-1").is_err());
+    }
+
+    #[test]
+    fn verify_parse_seconds() {
+        assert_eq!(parse_seconds("60"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_seconds("0.5"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_seconds("0"), Ok(Duration::ZERO));
+        assert!(parse_seconds("-1").is_err());
+        assert!(parse_seconds("NaN").is_err());
+        assert!(parse_seconds("inf").is_err());
+        assert!(parse_seconds("soon").is_err());
+    }
 }
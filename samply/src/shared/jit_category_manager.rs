@@ -2,6 +2,10 @@ use fxprof_processed_profile::{
     CategoryColor, CategoryHandle, CategoryPairHandle, Profile, StringHandle,
 };
 
+/// Namespaces of the .NET base class library and runtime. Methods in these namespaces
+/// are put into the "Framework" subcategory, everything else into "User code".
+const MANAGED_FRAMEWORK_NAMESPACES: &[&str] = &["System", "Microsoft", "Internal"];
+
 #[derive(Debug, Clone, Copy)]
 pub enum JsFrame {
     #[allow(dead_code)]
@@ -233,6 +237,53 @@ impl JitCategoryManager {
     }
 }
 
+/// Splits managed JIT code into "Framework" and "User code" subcategories of a
+/// single category, based on the method's namespace.
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // only used on Windows, where CoreCLR method events include the namespace
+pub struct ManagedCodeSubcategories {
+    category: CategoryHandle,
+    framework: Option<CategoryPairHandle>,
+    user: Option<CategoryPairHandle>,
+}
+
+#[allow(dead_code)]
+impl ManagedCodeSubcategories {
+    pub fn new(category: CategoryHandle) -> Self {
+        Self {
+            category,
+            framework: None,
+            user: None,
+        }
+    }
+
+    /// Get the subcategory for a method in `namespace`, e.g. "System.Collections.Generic.List`1".
+    ///
+    /// The subcategories are only created in the profile once they are needed.
+    pub fn classify(&mut self, namespace: &str, profile: &mut Profile) -> CategoryPairHandle {
+        let category = self.category;
+        if Self::is_framework_namespace(namespace) {
+            *self
+                .framework
+                .get_or_insert_with(|| profile.add_subcategory(category, "Framework"))
+        } else {
+            *self
+                .user
+                .get_or_insert_with(|| profile.add_subcategory(category, "User code"))
+        }
+    }
+
+    fn is_framework_namespace(namespace: &str) -> bool {
+        MANAGED_FRAMEWORK_NAMESPACES
+            .iter()
+            .any(|framework_namespace| {
+                namespace
+                    .strip_prefix(framework_namespace)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+    }
+}
+
 #[derive(Debug, Clone)]
 struct LazilyCreatedCategory {
     name: &'static str,
@@ -281,4 +332,27 @@ mod test {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn test_managed_code_subcategories() {
+        let mut profile = Profile::new(
+            "",
+            ReferenceTimestamp::from_millis_since_unix_epoch(0.0),
+            SamplingInterval::from_millis(1),
+        );
+        let category = profile.add_category("CoreCLR JIT", CategoryColor::Blue);
+        let mut subcategories = ManagedCodeSubcategories::new(category);
+        let framework = subcategories.classify("System.Collections.Generic.List`1", &mut profile);
+        let user = subcategories.classify("MyApp.Program", &mut profile);
+        assert_ne!(framework, user);
+        assert_eq!(
+            subcategories.classify("Microsoft.Extensions.Hosting", &mut profile),
+            framework
+        );
+        assert_eq!(
+            subcategories.classify("SystemMonitor.Worker", &mut profile),
+            user
+        );
+        assert_eq!(subcategories.classify("", &mut profile), user);
+    }
 }
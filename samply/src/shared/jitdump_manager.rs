@@ -102,6 +102,13 @@ impl JitDumpManager {
     }
 }
 
+/// Processes the records of a single jitdump file.
+///
+/// The symbol table for the jitdump library is only set on the profile when the
+/// processor is closed, i.e. when a JIT_CODE_CLOSE record is encountered or when
+/// [`SingleJitDumpProcessor::finish`] is called. `finish` must be called before
+/// the processor is dropped, otherwise the JIT symbols are lost; dropping an
+/// unfinished processor logs a warning with the number of lost symbols.
 #[derive(Debug)]
 struct SingleJitDumpProcessor {
     /// Some() until a JIT_CODE_CLOSE record is encountered.
@@ -257,6 +264,50 @@ impl SingleJitDumpProcessor {
 
     pub fn finish(mut self, profile: &mut Profile) -> LibMappingOpQueue {
         self.close_and_commit_symbol_table(profile);
-        self.lib_mapping_ops
+        std::mem::take(&mut self.lib_mapping_ops)
+    }
+}
+
+impl Drop for SingleJitDumpProcessor {
+    fn drop(&mut self) {
+        let is_closed = self.reader.is_none();
+        let panicking = std::thread::panicking();
+        if let Some(warning) = unfinished_drop_warning(is_closed, self.symbols.len(), panicking) {
+            log::warn!("{warning}");
+        }
+    }
+}
+
+/// Returns the warning for dropping a processor, if it's dropped before it was closed
+/// by `finish` or a JIT_CODE_CLOSE record. No warning is given during a panic, where
+/// the processor is expected to be dropped early.
+fn unfinished_drop_warning(
+    is_closed: bool,
+    symbol_count: usize,
+    panicking: bool,
+) -> Option<String> {
+    if is_closed || panicking {
+        return None;
+    }
+    Some(format!(
+        "SingleJitDumpProcessor dropped without calling finish(); {symbol_count} JIT symbols were lost"
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unfinished_drop_warning() {
+        assert_eq!(
+            unfinished_drop_warning(false, 3, false).as_deref(),
+            Some(
+                "SingleJitDumpProcessor dropped without calling finish(); 3 JIT symbols were lost"
+            )
+        );
+        // Finished or closed processors have handed their symbols to the profile.
+        assert_eq!(unfinished_drop_warning(true, 0, false), None);
+        assert_eq!(unfinished_drop_warning(false, 3, true), None);
     }
 }
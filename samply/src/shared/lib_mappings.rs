@@ -139,6 +139,16 @@ impl LibMappingOpQueue {
         self.0.push((timestamp, op));
     }
 
+    /// Adds ops which may be older than the ops already in the queue. They go after
+    /// all the ops with the same or an earlier timestamp, in the order given.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn insert_all(&mut self, timestamp: u64, ops: Vec<LibMappingOp>) {
+        self.0.extend(ops.into_iter().map(|op| (timestamp, op)));
+        // The queue and the new ops are both sorted, and the stable sort merges
+        // these two runs in linear time.
+        self.0.sort_by_key(|(t, _)| *t);
+    }
+
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
@@ -223,3 +233,99 @@ pub struct LibMappingMove {
 pub struct LibMappingRemove {
     pub start_avma: u64,
 }
+
+#[cfg(test)]
+mod test {
+    use debugid::DebugId;
+    use fxprof_processed_profile::{LibraryInfo, Profile, ReferenceTimestamp, SamplingInterval};
+
+    use super::*;
+
+    #[test]
+    fn test_remove_jit_mapping() {
+        let mut profile = Profile::new(
+            "",
+            ReferenceTimestamp::from_millis_since_unix_epoch(0.0),
+            SamplingInterval::from_millis(1),
+        );
+        let lib_handle = profile.add_lib(LibraryInfo {
+            name: "JIT".to_string(),
+            debug_name: "JIT".to_string(),
+            path: "JIT".to_string(),
+            debug_path: "JIT".to_string(),
+            debug_id: DebugId::nil(),
+            code_id: None,
+            arch: None,
+            symbol_table: None,
+        });
+
+        let mut ops = LibMappingOpQueue::default();
+        ops.push(
+            10,
+            LibMappingOp::Add(LibMappingAdd {
+                start_avma: 0x1000,
+                end_avma: 0x1100,
+                relative_address_at_start: 0x40,
+                info: LibMappingInfo::new_lib(lib_handle),
+            }),
+        );
+        // Removing an address that was never loaded does nothing.
+        ops.push(
+            15,
+            LibMappingOp::Remove(LibMappingRemove { start_avma: 0x5000 }),
+        );
+        ops.push(
+            20,
+            LibMappingOp::Remove(LibMappingRemove { start_avma: 0x1000 }),
+        );
+
+        let mut mappings = LibMappingsHierarchy::new(LibMappingOpQueue::default());
+        mappings.add_jitdump_lib_mappings_ops(ops);
+        mappings.process_ops(15);
+        let (relative_address, _info) = mappings.convert_address(0x1010).unwrap();
+        assert_eq!(relative_address, 0x50);
+        mappings.process_ops(20);
+        assert!(mappings.convert_address(0x1010).is_none());
+    }
+
+    #[test]
+    fn test_insert_older_op() {
+        let mut profile = Profile::new(
+            "test",
+            ReferenceTimestamp::from_millis_since_unix_epoch(0.0),
+            SamplingInterval::from_millis(1),
+        );
+        let lib_handle = profile.add_lib(LibraryInfo {
+            name: "JIT".to_string(),
+            debug_name: "JIT".to_string(),
+            path: "JIT".to_string(),
+            debug_path: "JIT".to_string(),
+            debug_id: DebugId::nil(),
+            code_id: None,
+            arch: None,
+            symbol_table: None,
+        });
+        let add = |start_avma, relative_address_at_start| {
+            LibMappingOp::Add(LibMappingAdd {
+                start_avma,
+                end_avma: start_avma + 0x100,
+                relative_address_at_start,
+                info: LibMappingInfo::new_lib(lib_handle),
+            })
+        };
+
+        let mut ops = LibMappingOpQueue::default();
+        ops.push(5, LibMappingOp::Clear);
+        ops.push(20, add(0x1000, 0x0));
+        ops.insert_all(5, vec![add(0x2000, 0x100), add(0x3000, 0x200)]);
+
+        let mut mappings = LibMappingsHierarchy::new(LibMappingOpQueue::default());
+        mappings.add_jitdump_lib_mappings_ops(ops);
+        mappings.process_ops(10);
+        let (relative_address, _info) = mappings.convert_address(0x2010).unwrap();
+        assert_eq!(relative_address, 0x110);
+        let (relative_address, _info) = mappings.convert_address(0x3010).unwrap();
+        assert_eq!(relative_address, 0x210);
+        assert!(mappings.convert_address(0x1010).is_none());
+    }
+}
@@ -4,13 +4,66 @@ use std::time::Duration;
 
 use serde_derive::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct CoreClrProfileProps {
     pub enabled: bool,
     pub gc_markers: bool,
     pub gc_suspensions: bool,
     pub gc_detailed_allocs: bool,
+    /// Which sampled allocation keywords to enable if `gc_detailed_allocs` is set.
+    pub gc_alloc_sampling: CoreClrAllocSampling,
     pub event_stacks: bool,
+    /// Emit a marker the first time each ReadyToRun method's entry point is used.
+    pub r2r_markers: bool,
+    /// Put GC allocation, pause and finalizer markers into separate categories,
+    /// instead of using a single "CoreCLR GC" category for all of them.
+    pub gc_categories: bool,
+    /// Track GC handle creation and destruction, as markers and a live handle counter.
+    pub gc_handles: bool,
+    /// Don't emit markers for rundown (DCStart / DCEnd) events. They're still used to
+    /// add JIT symbols. When attaching, rundown lists everything that's already loaded,
+    /// which would otherwise show up as a burst of markers at the start of the profile.
+    pub suppress_rundown_markers: bool,
+    /// Request BulkType events, so that allocation markers can show type names instead
+    /// of TypeIDs. This adds some overhead: the runtime logs a BulkType event for every
+    /// type the first time it's referenced by an allocation or a GC heap walk.
+    pub resolve_type_names: bool,
+    /// When attaching, also request an end rundown (DCEnd), which lists the methods that
+    /// the start rundown missed. It repeats the start rundown's method list, so it roughly
+    /// doubles the rundown volume.
+    pub end_rundown: bool,
+    /// Additional `(provider, keywords, level)` entries to enable, on top of the
+    /// providers derived from the flags above. Keywords of entries for the same
+    /// provider are merged.
+    pub extra_providers: Vec<(String, u64, u8)>,
+}
+
+/// Selects the GCSampledObjectAllocation keywords. "High" gets an event for (almost)
+/// every allocation and is very expensive, "Low" only samples every 100KB or so.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoreClrAllocSampling {
+    #[default]
+    Low,
+    High,
+    Both,
+}
+
+/// Restricts CoreCLR markers to a subset of threads. JIT symbols are per-process
+/// and are added regardless of which thread loaded the method.
+#[derive(Debug, Default, Clone)]
+pub struct CoreClrThreadFilter {
+    /// If not empty, only these threads get markers.
+    pub include: Vec<u32>,
+    /// These threads never get markers.
+    pub exclude: Vec<u32>,
+}
+
+impl CoreClrThreadFilter {
+    // Only the ETW CoreCLR code filters by thread.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn includes(&self, tid: u32) -> bool {
+        (self.include.is_empty() || self.include.contains(&tid)) && !self.exclude.contains(&tid)
+    }
 }
 
 impl CoreClrProfileProps {
@@ -20,6 +73,9 @@ impl CoreClrProfileProps {
             || self.gc_suspensions
             || self.gc_detailed_allocs
             || self.event_stacks
+            || self.r2r_markers
+            || self.gc_handles
+            || self.resolve_type_names
     }
 }
 
@@ -75,6 +131,12 @@ pub struct ProfileCreationProps {
     pub reuse_threads: bool,
     /// Fold repeated frames at the base of the stack.
     pub fold_recursive_prefix: bool,
+    /// Merge adjacent JIT symbols which have the same name.
+    pub coalesce_jit_symbols: bool,
+    /// The maximum number of symbols per synthetic JIT library.
+    pub max_jit_symbols: Option<usize>,
+    /// The relative address at which the symbols of synthetic JIT libraries start.
+    pub jit_base_address: u32,
     /// Unlink jitdump/marker files
     pub unlink_aux_files: bool,
     /// Create a separate thread for each CPU.
@@ -86,12 +148,17 @@ pub struct ProfileCreationProps {
     pub override_arch: Option<String>,
     /// Dump presymbolication info.
     pub unstable_presymbolicate: bool,
+    /// How long to wait for the symbols of a single library during presymbolication.
+    pub presymbolicate_timeout: std::time::Duration,
     /// CoreCLR specific properties.
     #[allow(dead_code)]
     pub coreclr: CoreClrProfileProps,
     /// Create markers for unknown events.
     #[allow(dead_code)]
     pub unknown_event_markers: bool,
+    /// Which threads to create CoreCLR markers for.
+    #[allow(dead_code)]
+    pub coreclr_thread_filter: CoreClrThreadFilter,
     /// Time range to include, relative to start of recording.
     #[allow(dead_code)]
     pub time_range: Option<(std::time::Duration, std::time::Duration)>,
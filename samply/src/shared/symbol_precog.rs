@@ -1,8 +1,13 @@
 use std::fs::File;
+use std::future::Future;
 use std::io::BufWriter;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::{collections::HashMap, path::Path};
+use std::time::Duration;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
 
 use debugid::DebugId;
 use serde::{
@@ -152,8 +157,8 @@ struct PrecogLibrarySymbols {
     debug_id: String,
     code_id: String,
     symbol_table: Vec<InternedSymbolInfo>,
-    // vector of (rva, index in symbol_table) so that multiple addresses
-    // within a function map to the same symbol
+    // vector of (rva, index in symbol_table), sorted by rva, so that multiple
+    // addresses within a function map to the same symbol
     known_addresses: Vec<(u32, usize)>,
 
     #[serde(skip)]
@@ -211,6 +216,8 @@ impl<'de> Deserialize<'de> for PrecogSymbolInfo {
                 let string_table = Arc::new(string_table);
                 for lib in &mut data {
                     lib.string_table = Some(string_table.clone());
+                    // Lookups binary search this list; files written by older versions may not be sorted.
+                    lib.known_addresses.sort_unstable();
                 }
                 Ok(PrecogSymbolInfo { string_table, data })
             }
@@ -232,6 +239,31 @@ impl PrecogLibrarySymbols {
     fn get_owned_opt_string(&self, index: Option<StringTableIndex>) -> Option<String> {
         index.map(|index| self.get_string(index).to_owned())
     }
+
+    fn address_info(&self, sym_index: usize) -> wholesym::SyncAddressInfo {
+        let info = &self.symbol_table[sym_index];
+        wholesym::SyncAddressInfo {
+            symbol: wholesym::SymbolInfo {
+                address: info.rva,
+                size: info.size,
+                name: self.get_owned_string(info.symbol),
+            },
+            frames: info.frames.as_ref().map(|frames| {
+                wholesym::FramesLookupResult::Available(
+                    frames
+                        .iter()
+                        .map(|frame| wholesym::FrameDebugInfo {
+                            function: self.get_owned_opt_string(frame.function),
+                            file_path: frame.file.map(|file| {
+                                SourceFilePath::new(self.get_string(file).to_owned(), None)
+                            }),
+                            line_number: frame.line,
+                        })
+                        .collect(),
+                )
+            }),
+        }
+    }
 }
 
 impl wholesym::samply_symbols::SymbolMapTrait for PrecogLibrarySymbols {
@@ -258,37 +290,13 @@ impl wholesym::samply_symbols::SymbolMapTrait for PrecogLibrarySymbols {
     fn lookup_sync(&self, address: wholesym::LookupAddress) -> Option<wholesym::SyncAddressInfo> {
         match address {
             wholesym::LookupAddress::Relative(rva) => {
-                for (known_rva, sym_index) in &self.known_addresses {
-                    if *known_rva == rva {
-                        //eprintln!("lookup_sync: 0x{:x} -> {}", rva, info.symbol.0);
-                        let info = &self.symbol_table[*sym_index];
-                        return Some(wholesym::SyncAddressInfo {
-                            symbol: wholesym::SymbolInfo {
-                                address: info.rva,
-                                size: info.size,
-                                name: self.get_owned_string(info.symbol),
-                            },
-                            frames: info.frames.as_ref().map(|frames| {
-                                wholesym::FramesLookupResult::Available(
-                                    frames
-                                        .iter()
-                                        .map(|frame| wholesym::FrameDebugInfo {
-                                            function: self.get_owned_opt_string(frame.function),
-                                            file_path: frame.file.map(|file| {
-                                                SourceFilePath::new(
-                                                    self.get_string(file).to_owned(),
-                                                    None,
-                                                )
-                                            }),
-                                            line_number: frame.line,
-                                        })
-                                        .collect(),
-                                )
-                            }),
-                        });
-                    }
-                }
-                None
+                let index = self
+                    .known_addresses
+                    .binary_search_by_key(&rva, |(known_rva, _)| *known_rva)
+                    .ok()?;
+                let (_, sym_index) = self.known_addresses[index];
+                //eprintln!("lookup_sync: 0x{:x} -> {}", rva, sym_index);
+                Some(self.address_info(sym_index))
             }
             wholesym::LookupAddress::Svma(_) => None,
             wholesym::LookupAddress::FileOffset(_) => None,
@@ -325,7 +333,56 @@ impl PrecogSymbolInfo {
     }
 }
 
-pub fn presymbolicate(profile: &fxprof_processed_profile::Profile, precog_output: &Path) {
+/// Awaits `future`, but gives up after `timeout`, so that a slow or unreachable
+/// symbol server can't hold up presymbolication forever.
+async fn with_timeout<T>(
+    lib_name: &str,
+    timeout: Duration,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    match tokio::time::timeout(timeout, future).await {
+        Ok(result) => Some(result),
+        Err(_) => {
+            eprintln!(
+                "Warning: Getting symbols for {lib_name} took longer than {timeout:?}, skipping it."
+            );
+            None
+        }
+    }
+}
+
+/// Groups the used libraries by debug ID. A library can appear more than once, for
+/// example if it was loaded at multiple addresses; its RVAs are merged so that its
+/// symbols are only resolved and written once.
+///
+/// Libraries without a debug ID are never merged: a nil debug ID doesn't identify a
+/// binary, so merging them would attribute symbols to the wrong library.
+fn used_libs_by_debug_id<'a>(
+    used_libs: impl Iterator<Item = (&'a fxprof_processed_profile::LibraryInfo, &'a BTreeSet<u32>)>,
+) -> Vec<(&'a fxprof_processed_profile::LibraryInfo, BTreeSet<u32>)> {
+    let mut result: Vec<(&fxprof_processed_profile::LibraryInfo, BTreeSet<u32>)> = Vec::new();
+    let mut index_for_debug_id: HashMap<DebugId, usize> = HashMap::new();
+    for (lib, rvas) in used_libs {
+        if lib.debug_id.is_nil() {
+            result.push((lib, rvas.clone()));
+            continue;
+        }
+        match index_for_debug_id.get(&lib.debug_id) {
+            Some(&index) => result[index].1.extend(rvas),
+            None => {
+                index_for_debug_id.insert(lib.debug_id, result.len());
+                result.push((lib, rvas.clone()));
+            }
+        }
+    }
+    result
+}
+
+pub fn presymbolicate(
+    profile: &fxprof_processed_profile::Profile,
+    precog_output: &Path,
+    timeout_per_lib: Duration,
+) {
     let rt = tokio::runtime::Runtime::new().unwrap();
 
     let mut string_table = StringTable::new();
@@ -337,7 +394,7 @@ pub fn presymbolicate(profile: &fxprof_processed_profile::Profile, precog_output
         .respect_nt_symbol_path(true);
     let mut symbol_manager = wholesym::SymbolManager::with_config(config);
 
-    for (lib, rvas) in profile.lib_used_rva_iter() {
+    for (lib, rvas) in used_libs_by_debug_id(profile.lib_used_rva_iter()) {
         // Add the library to the symbol manager with all the info, so that load_symbol_map can find it later
         symbol_manager.add_known_library(wholesym::LibraryInfo {
             name: Some(lib.debug_name.clone()),
@@ -354,7 +411,7 @@ pub fn presymbolicate(profile: &fxprof_processed_profile::Profile, precog_output
 
         //eprintln!("Library {} ({}) has {} rvas", lib.debug_name, lib.debug_id, rvas.len());
 
-        let result = rt.block_on(async {
+        let result = rt.block_on(with_timeout(&lib.debug_name, timeout_per_lib, async {
             let Ok(symbol_map) = symbol_manager
                 .load_symbol_map(&lib.debug_name, lib.debug_id)
                 .await
@@ -367,7 +424,7 @@ pub fn presymbolicate(profile: &fxprof_processed_profile::Profile, precog_output
             let mut symbol_table_map = HashMap::new();
 
             let mut known_addresses = Vec::new();
-            for rva in rvas {
+            for rva in &rvas {
                 if let Some(addr_info) = symbol_map
                     .lookup(wholesym::LookupAddress::Relative(*rva))
                     .await
@@ -383,6 +440,9 @@ pub fn presymbolicate(profile: &fxprof_processed_profile::Profile, precog_output
                 }
             }
 
+            known_addresses.sort_unstable();
+            known_addresses.dedup_by_key(|(rva, _)| *rva);
+
             Some(PrecogLibrarySymbols {
                 debug_name: lib.debug_name.clone(),
                 debug_id: lib.debug_id.to_string(),
@@ -395,9 +455,9 @@ pub fn presymbolicate(profile: &fxprof_processed_profile::Profile, precog_output
                 known_addresses,
                 string_table: None,
             })
-        });
+        }));
 
-        if let Some(result) = result {
+        if let Some(Some(result)) = result {
             results.push(result);
         }
     }
@@ -417,3 +477,122 @@ pub fn presymbolicate(profile: &fxprof_processed_profile::Profile, precog_output
         to_writer(writer, &info).expect("Couldn't write JSON for presymbolication");
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use wholesym::samply_symbols::SymbolMapTrait;
+    use wholesym::LookupAddress;
+
+    fn lib(name: &str, debug_id: DebugId) -> fxprof_processed_profile::LibraryInfo {
+        fxprof_processed_profile::LibraryInfo {
+            name: name.to_owned(),
+            debug_name: name.to_owned(),
+            path: name.to_owned(),
+            debug_path: name.to_owned(),
+            debug_id,
+            code_id: None,
+            arch: None,
+            symbol_table: None,
+        }
+    }
+
+    #[test]
+    fn test_used_libs_by_debug_id() {
+        let id_a = DebugId::from_str("11111111-1111-1111-1111-111111111111-1").unwrap();
+        let id_b = DebugId::from_str("22222222-2222-2222-2222-222222222222-2").unwrap();
+        let (a1, a2, b) = (lib("a.dll", id_a), lib("a.dll", id_a), lib("b.dll", id_b));
+        let (rvas_a1, rvas_a2, rvas_b) = (
+            BTreeSet::from([0x10, 0x20]),
+            BTreeSet::from([0x20, 0x30]),
+            BTreeSet::from([0x40]),
+        );
+        let libs =
+            used_libs_by_debug_id([(&a1, &rvas_a1), (&b, &rvas_b), (&a2, &rvas_a2)].into_iter());
+        assert_eq!(libs.len(), 2);
+        assert_eq!(libs[0].0.debug_id, id_a);
+        assert_eq!(libs[0].1, BTreeSet::from([0x10, 0x20, 0x30]));
+        assert_eq!(libs[1].0.debug_id, id_b);
+        assert_eq!(libs[1].1, rvas_b);
+    }
+
+    #[test]
+    fn test_used_libs_with_nil_debug_id_are_not_merged() {
+        let (jit, elf) = (lib("JIT", DebugId::nil()), lib("libfoo.so", DebugId::nil()));
+        let (rvas_jit, rvas_elf) = (BTreeSet::from([0x10]), BTreeSet::from([0x20]));
+        let libs = used_libs_by_debug_id([(&jit, &rvas_jit), (&elf, &rvas_elf)].into_iter());
+        assert_eq!(libs.len(), 2);
+        assert_eq!(libs[0].0.name, "JIT");
+        assert_eq!(libs[0].1, rvas_jit);
+        assert_eq!(libs[1].0.name, "libfoo.so");
+        assert_eq!(libs[1].1, rvas_elf);
+    }
+
+    #[test]
+    fn test_with_timeout() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let timeout = Duration::from_millis(10);
+        assert_eq!(
+            rt.block_on(with_timeout("fast.pdb", timeout, async { 5 })),
+            Some(5)
+        );
+        // A symbol source that never responds is abandoned.
+        let never_loads = std::future::pending::<Option<PrecogLibrarySymbols>>();
+        assert!(rt
+            .block_on(with_timeout("slow.pdb", timeout, never_loads))
+            .is_none());
+    }
+
+    #[test]
+    fn test_lookup_sync() {
+        let mut string_table = StringTable::new();
+        let symbol_table = vec![
+            InternedSymbolInfo {
+                rva: 0x1000,
+                size: Some(0x100),
+                symbol: string_table.intern_string("first"),
+                frames: None,
+            },
+            InternedSymbolInfo {
+                rva: 0x2000,
+                size: None,
+                symbol: string_table.intern_string("second"),
+                frames: None,
+            },
+        ];
+        let lib = PrecogLibrarySymbols {
+            debug_name: "test.pdb".to_owned(),
+            debug_id: DebugId::nil().to_string(),
+            code_id: String::new(),
+            symbol_table,
+            known_addresses: vec![(0x1000, 0), (0x1010, 0), (0x2040, 1)],
+            string_table: Some(Arc::new(string_table)),
+        };
+
+        let addresses = [
+            LookupAddress::Relative(0x2040),
+            LookupAddress::Relative(0x1010),
+            LookupAddress::Relative(0x1500),
+            LookupAddress::Svma(0x1000),
+            LookupAddress::Relative(0x1000),
+            LookupAddress::Relative(0x3000),
+            LookupAddress::Relative(0x1010),
+        ];
+        let names: Vec<Option<String>> = addresses
+            .iter()
+            .map(|a| lib.lookup_sync(*a).map(|info| info.symbol.name))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                Some("second".to_owned()),
+                Some("first".to_owned()),
+                None,
+                None,
+                Some("first".to_owned()),
+                None,
+                Some("first".to_owned()),
+            ]
+        );
+    }
+}
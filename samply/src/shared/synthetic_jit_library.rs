@@ -1,35 +1,59 @@
+use std::hash::Hasher;
 use std::sync::Arc;
 
-use debugid::DebugId;
+use debugid::{CodeId, DebugId};
+use fxhash::FxHasher;
 use fxprof_processed_profile::{
     CategoryPairHandle, LibraryHandle, LibraryInfo, Profile, Symbol, SymbolTable,
 };
 
 use super::types::FastHashMap;
 
+/// A library for JIT code which has no file on disk, with a symbol table built from
+/// the functions that are added to it.
+///
+/// Functions are laid out contiguously in the library's "relative address" space,
+/// in the order they're added, starting at the base relative address (0 by default).
+/// The returned relative addresses are what goes into the lib mapping for the function's
+/// real address range (its AVMA range), with the start of that range mapping to the
+/// function's relative address. The relative addresses have no relation to the real
+/// addresses; a function can be loaded at several addresses and still get one symbol.
 #[derive(Debug)]
 pub struct SyntheticJitLibrary {
+    name: String,
     lib_handle: LibraryHandle,
     default_category: CategoryPairHandle,
     next_relative_address: u32,
+    /// Set once a function didn't get a symbol, either because it didn't fit into the
+    /// relative address space or because of the symbol limit.
+    is_full: bool,
+    max_symbols: Option<usize>,
     symbols: Vec<Symbol>,
     recycler: Option<FastHashMap<(String, u32), u32>>,
+    coalesce_symbols: bool,
 }
 
 impl SyntheticJitLibrary {
+    /// `pid` and `start_timestamp_raw` identify the process whose JIT code goes into this
+    /// library; they go into the library's debug ID and code ID.
     pub fn new(
         name: String,
+        pid: u32,
+        start_timestamp_raw: u64,
         default_category: CategoryPairHandle,
         profile: &mut Profile,
         allow_recycling: bool,
+        coalesce_symbols: bool,
     ) -> Self {
+        let (debug_id, code_id_bytes) =
+            debug_id_and_code_id_for_synthetic_jit(&name, pid, start_timestamp_raw);
         let lib_handle = profile.add_lib(LibraryInfo {
             name: name.clone(),
             debug_name: name.clone(),
             path: name.clone(),
-            debug_path: name,
-            debug_id: DebugId::nil(),
-            code_id: None,
+            debug_path: name.clone(),
+            debug_id,
+            code_id: Some(CodeId::from_binary(&code_id_bytes).to_string()),
             arch: None,
             symbol_table: None,
         });
@@ -39,39 +63,83 @@ impl SyntheticJitLibrary {
             None
         };
         Self {
+            name,
             lib_handle,
             default_category,
             next_relative_address: 0,
+            is_full: false,
+            max_symbols: None,
             symbols: Vec::new(),
             recycler,
+            coalesce_symbols,
         }
     }
 
+    /// Makes functions start at `base` instead of 0 in the relative address space, e.g.
+    /// to keep them clear of another range that a consumer uses. Must be called before
+    /// any functions are added.
+    pub fn set_base_relative_address(&mut self, base: u32) {
+        assert!(
+            self.symbols.is_empty(),
+            "The base relative address must be set before adding functions"
+        );
+        self.next_relative_address = base;
+    }
+
+    /// Limits the number of symbols in this library, to keep the symbol table of a
+    /// trace with a huge number of JIT functions from using up all memory. `None`
+    /// means no limit.
+    pub fn set_max_symbols(&mut self, max_symbols: Option<usize>) {
+        self.max_symbols = max_symbols;
+    }
+
     /// Returns the relative address of the added function.
-    pub fn add_function(&mut self, name: String, size: u32) -> u32 {
-        if let Some(recycler) = self.recycler.as_mut() {
-            let key = (name, size);
-            if let Some(relative_address) = recycler.get(&key) {
-                return *relative_address;
+    ///
+    /// The relative address only depends on the sequence of `add_function` calls before
+    /// this one, so processing the same trace twice assigns the same addresses.
+    ///
+    /// Returns `None` if the function doesn't fit into the library's 32-bit relative
+    /// address space any more, which takes around 4GB of (non-recycled) JIT code, or if
+    /// the library already has the maximum number of symbols. The function is then left
+    /// without a symbol. Recycled functions are still found in either case.
+    pub fn add_function(&mut self, name: String, size: u32) -> Option<u32> {
+        let key = (name, size);
+        if let Some(relative_address) = self.recycler.as_ref().and_then(|r| r.get(&key)) {
+            return Some(*relative_address);
+        }
+
+        if let Some(max_symbols) = self.max_symbols {
+            if self.symbols.len() >= max_symbols {
+                self.warn_full(&format!(
+                    "Warning: More than {max_symbols} JIT functions, some won't have symbols."
+                ));
+                return None;
             }
-            let relative_address = self.next_relative_address;
-            self.next_relative_address += size;
-            self.symbols.push(Symbol {
-                address: relative_address,
-                size: Some(size),
-                name: key.0.clone(),
-            });
-            recycler.insert(key, relative_address);
-            relative_address
-        } else {
-            let relative_address = self.next_relative_address;
-            self.next_relative_address += size;
-            self.symbols.push(Symbol {
-                address: relative_address,
-                size: Some(size),
-                name,
-            });
-            relative_address
+        }
+
+        let relative_address = self.next_relative_address;
+        let Some(next_relative_address) = relative_address.checked_add(size) else {
+            self.warn_full("Warning: Too much JIT code, some JIT functions won't have symbols.");
+            return None;
+        };
+        self.next_relative_address = next_relative_address;
+
+        let (name, size) = key;
+        if let Some(recycler) = self.recycler.as_mut() {
+            recycler.insert((name.clone(), size), relative_address);
+        }
+        self.symbols.push(Symbol {
+            address: relative_address,
+            size: Some(size),
+            name,
+        });
+        Some(relative_address)
+    }
+
+    fn warn_full(&mut self, message: &str) {
+        if !self.is_full {
+            eprintln!("{message}");
+            self.is_full = true;
         }
     }
 
@@ -84,7 +152,200 @@ impl SyntheticJitLibrary {
     }
 
     pub fn finish_and_set_symbol_table(self, profile: &mut Profile) {
-        let symbol_table = Arc::new(SymbolTable::new(self.symbols));
-        profile.set_lib_symbol_table(self.lib_handle, symbol_table);
+        let symbols = if self.coalesce_symbols {
+            coalesce_adjacent_symbols(self.symbols)
+        } else {
+            self.symbols
+        };
+        let (symbol_table, clamped_count) = SymbolTable::new_with_clamped_count(symbols);
+        if clamped_count != 0 {
+            log::warn!(
+                "{clamped_count} overlapping symbols in the symbol table for {}",
+                self.name
+            );
+        }
+        profile.set_lib_symbol_table(self.lib_handle, Arc::new(symbol_table));
+    }
+}
+
+/// Creates an identity for a synthetic JIT library, similar to the one for jitdump
+/// files. The library name, the pid and the process start timestamp go into it, so
+/// that different JIT libraries don't share a debug ID, neither within a profile nor
+/// across profiles. A shared debug ID would mix up their symbols in presymbolication.
+///
+/// All inputs come from the trace, so converting the same trace twice gives the same
+/// identity.
+fn debug_id_and_code_id_for_synthetic_jit(
+    name: &str,
+    pid: u32,
+    start_timestamp_raw: u64,
+) -> (DebugId, [u8; 20]) {
+    let mut hasher = FxHasher::default();
+    hasher.write(name.as_bytes());
+    let name_hash = hasher.finish() as u32;
+
+    let mut code_id_bytes = [0; 20];
+    code_id_bytes[0..4].copy_from_slice(&pid.to_le_bytes());
+    code_id_bytes[4..12].copy_from_slice(&start_timestamp_raw.to_le_bytes());
+    code_id_bytes[12..16].copy_from_slice(&name_hash.to_le_bytes());
+    code_id_bytes[16..20].copy_from_slice(b"SJIT");
+    let debug_id = DebugId::from_guid_age(&code_id_bytes[..16], 0).unwrap();
+    (debug_id, code_id_bytes)
+}
+
+/// Merges symbols which have the same name and which are directly adjacent to
+/// each other, i.e. where one symbol ends at the address where the next one
+/// starts. This makes the symbol table smaller, but it loses the distinction
+/// between separate loads of the same function.
+fn coalesce_adjacent_symbols(mut symbols: Vec<Symbol>) -> Vec<Symbol> {
+    symbols.sort_by_key(|symbol| symbol.address);
+    let mut coalesced: Vec<Symbol> = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        if let Some(prev) = coalesced.last_mut() {
+            if let (Some(prev_size), Some(size)) = (prev.size, symbol.size) {
+                if prev.name == symbol.name && prev.address + prev_size == symbol.address {
+                    prev.size = Some(prev_size + size);
+                    continue;
+                }
+            }
+        }
+        coalesced.push(symbol);
+    }
+    coalesced
+}
+
+#[cfg(test)]
+mod test {
+    use fxprof_processed_profile::{CategoryColor, ReferenceTimestamp, SamplingInterval};
+
+    use super::*;
+
+    fn symbol(address: u32, size: u32, name: &str) -> Symbol {
+        Symbol {
+            address,
+            size: Some(size),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_coalesce_adjacent_symbols() {
+        let symbols = coalesce_adjacent_symbols(vec![
+            symbol(0x10, 0x10, "Foo"),
+            symbol(0x0, 0x10, "Foo"),
+            symbol(0x20, 0x8, "Bar"),
+            symbol(0x30, 0x8, "Bar"),
+        ]);
+        assert_eq!(
+            symbols,
+            vec![
+                symbol(0x0, 0x20, "Foo"),
+                symbol(0x20, 0x8, "Bar"),
+                symbol(0x30, 0x8, "Bar"),
+            ]
+        );
+    }
+
+    fn new_lib(allow_recycling: bool) -> SyntheticJitLibrary {
+        let mut profile = Profile::new(
+            "test",
+            ReferenceTimestamp::from_millis_since_unix_epoch(0.0),
+            SamplingInterval::from_millis(1),
+        );
+        let category = profile.add_category("JIT", CategoryColor::Green).into();
+        SyntheticJitLibrary::new(
+            "JIT".to_string(),
+            100,
+            0,
+            category,
+            &mut profile,
+            allow_recycling,
+            false,
+        )
+    }
+
+    #[test]
+    fn test_relative_addresses_are_deterministic() {
+        let functions = [("Foo", 0x20), ("Bar", 0x8), ("Foo", 0x20), ("Foo", 0x10)];
+        for allow_recycling in [false, true] {
+            let mut addresses = vec![];
+            let mut symbols = vec![];
+            for _ in 0..2 {
+                let mut lib = new_lib(allow_recycling);
+                addresses.push(
+                    functions
+                        .iter()
+                        .map(|(name, size)| lib.add_function(name.to_string(), *size))
+                        .collect::<Vec<_>>(),
+                );
+                symbols.push(lib.symbols);
+            }
+            assert_eq!(addresses[0], addresses[1]);
+            assert_eq!(symbols[0], symbols[1]);
+        }
+    }
+
+    #[test]
+    fn test_relative_address_space_exhausted() {
+        let mut lib = new_lib(false);
+        assert_eq!(lib.add_function("A".to_string(), 0x8000_0000), Some(0));
+        assert_eq!(
+            lib.add_function("B".to_string(), 0x7fff_fff0),
+            Some(0x8000_0000)
+        );
+        // This one would end past u32::MAX.
+        assert_eq!(lib.add_function("C".to_string(), 0x20), None);
+        // Smaller functions still fit into the remaining space.
+        assert_eq!(lib.add_function("D".to_string(), 0xf), Some(0xffff_fff0));
+        assert_eq!(lib.add_function("E".to_string(), 0x1), None);
+        assert_eq!(lib.symbols.len(), 3);
+    }
+
+    #[test]
+    fn test_max_symbols() {
+        let mut lib = new_lib(true);
+        lib.set_max_symbols(Some(2));
+        assert_eq!(lib.add_function("Foo".to_string(), 0x20), Some(0));
+        assert_eq!(lib.add_function("Bar".to_string(), 0x8), Some(0x20));
+        assert_eq!(lib.add_function("Baz".to_string(), 0x4), None);
+        assert_eq!(lib.add_function("Qux".to_string(), 0x4), None);
+        // Functions which already have a symbol are still found.
+        assert_eq!(lib.add_function("Foo".to_string(), 0x20), Some(0));
+        assert_eq!(lib.symbols.len(), 2);
+        assert_eq!(lib.next_relative_address, 0x28);
+    }
+
+    #[test]
+    fn test_base_relative_address() {
+        let mut lib = new_lib(true);
+        lib.set_base_relative_address(0x10000);
+        assert_eq!(lib.add_function("Foo".to_string(), 0x20), Some(0x10000));
+        assert_eq!(lib.add_function("Bar".to_string(), 0x8), Some(0x10020));
+        // Recycled functions keep their offset address.
+        assert_eq!(lib.add_function("Foo".to_string(), 0x20), Some(0x10000));
+        assert_eq!(lib.add_function("Baz".to_string(), 0x4), Some(0x10028));
+    }
+
+    #[test]
+    fn test_synthetic_jit_debug_ids_are_distinct() {
+        let (js_id, js_code_id) = debug_id_and_code_id_for_synthetic_jit("JS JIT", 100, 1234);
+        let (clr_id, _) = debug_id_and_code_id_for_synthetic_jit("CoreCLR JIT", 100, 1234);
+        assert_ne!(js_id, clr_id);
+        assert_ne!(js_id, DebugId::nil());
+        assert_eq!(&js_code_id[16..20], b"SJIT");
+        assert_eq!(
+            debug_id_and_code_id_for_synthetic_jit("JS JIT", 100, 1234),
+            (js_id, js_code_id)
+        );
+    }
+
+    #[test]
+    fn test_processes_get_distinct_jit_libraries() {
+        let (first_id, _) = debug_id_and_code_id_for_synthetic_jit("CoreCLR JIT", 100, 1234);
+        let (other_pid_id, _) = debug_id_and_code_id_for_synthetic_jit("CoreCLR JIT", 200, 1234);
+        // A later process which reuses the pid.
+        let (reused_pid_id, _) = debug_id_and_code_id_for_synthetic_jit("CoreCLR JIT", 100, 5678);
+        assert_ne!(first_id, other_pid_id);
+        assert_ne!(first_id, reused_pid_id);
     }
 }
@@ -20,6 +20,12 @@ impl TimestampConverter {
         CpuDelta::from_nanos(delta_raw * self.raw_to_ns_factor)
     }
 
+    /// Converts the time between two raw timestamps into milliseconds.
+    #[allow(dead_code)]
+    pub fn convert_duration_ms(&self, start_raw: u64, end_raw: u64) -> f64 {
+        (end_raw.saturating_sub(start_raw) * self.raw_to_ns_factor) as f64 / 1_000_000.0
+    }
+
     #[allow(unused)]
     pub fn convert_us(&self, time_us: u64) -> Timestamp {
         Timestamp::from_nanos_since_reference(
@@ -1,9 +1,7 @@
-use std::{collections::HashMap, convert::TryInto, fmt::Display};
+use std::{collections::HashSet, convert::TryInto, fmt::Display};
 
 use bitflags::bitflags;
 use fxprof_processed_profile::*;
-use num_derive::FromPrimitive;
-use num_traits::FromPrimitive;
 
 use etw_reader::{self, schema::TypedEvent};
 use etw_reader::{
@@ -11,10 +9,14 @@ use etw_reader::{
     parser::{Parser, TryParse},
 };
 
-use crate::shared::recording_props::{CoreClrProfileProps, ProfileCreationProps};
+use crate::shared::recording_props::{
+    CoreClrAllocSampling, CoreClrProfileProps, CoreClrThreadFilter, ProfileCreationProps,
+};
+use crate::shared::types::FastHashMap;
 use crate::windows::profile_context::{KnownCategory, ProfileContext};
 
 use super::elevated_helper::ElevatedRecordingProps;
+use super::winutils::stack_addresses_from_bytes;
 
 struct SavedMarkerInfo {
     start_timestamp_raw: u64,
@@ -22,20 +24,338 @@ struct SavedMarkerInfo {
     description: String,
 }
 
+/// The fields of a GCStart event, saved until the matching GCEnd arrives.
+struct SavedGcStartInfo {
+    start_timestamp_raw: u64,
+    count: u32,
+    depth: u32,
+    reason: GcReason,
+    gc_type: GcType,
+}
+
+/// Keeps track of the ClrInstanceIDs seen in each process.
+///
+/// A process can host more than one CLR instance (in-proc side-by-side). Method
+/// loads carry real code addresses, so samples still resolve correctly, but the
+/// per-thread GC and marker state below isn't keyed by instance, so we warn if
+/// we see more than one.
+#[derive(Debug, Default)]
+struct ClrInstanceTracker {
+    first_instance_for_pid: FastHashMap<u32, u16>,
+    pids_with_multiple_instances: HashSet<u32>,
+}
+
+impl ClrInstanceTracker {
+    /// Returns true the first time a second ClrInstanceID is seen for `pid`.
+    fn observe(&mut self, pid: u32, clr_instance_id: u16) -> bool {
+        let first_instance = *self
+            .first_instance_for_pid
+            .entry(pid)
+            .or_insert(clr_instance_id);
+        first_instance != clr_instance_id && self.pids_with_multiple_instances.insert(pid)
+    }
+}
+
+/// How a method came to be listed in the trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MethodLoadKind {
+    /// The method was jitted (or re-jitted) during the capture.
+    Load,
+    /// Start rundown: the method already existed when the capture started.
+    DCStart,
+    /// End rundown: the method still existed when the capture ended. This repeats
+    /// every method we already know about from a Load or DCStart event.
+    DCEnd,
+}
+
+impl MethodLoadKind {
+    fn from_opcode(opcode: &str) -> Option<Self> {
+        match opcode {
+            "MethodLoadVerbose" => Some(MethodLoadKind::Load),
+            "MethodDCStartVerbose" => Some(MethodLoadKind::DCStart),
+            "MethodDCEndVerbose" => Some(MethodLoadKind::DCEnd),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps track of the method start addresses we've added a JIT symbol for, so that
+/// rundown events don't add the same method again.
+#[derive(Debug, Default)]
+struct KnownMethods {
+    start_addresses: HashSet<(u32, u64)>,
+}
+
+impl KnownMethods {
+    /// Forgets an unloaded method, so that a new method at the same address is added.
+    fn remove(&mut self, pid: u32, start_address: u64) {
+        self.start_addresses.remove(&(pid, start_address));
+    }
+
+    /// Returns whether a method listed by an event of the given kind needs to be added.
+    fn should_add(&mut self, pid: u32, start_address: u64, kind: MethodLoadKind) -> bool {
+        let is_new = self.start_addresses.insert((pid, start_address));
+        match kind {
+            // A method can be re-jitted at an address that was used by an unloaded method.
+            MethodLoadKind::Load => true,
+            MethodLoadKind::DCStart | MethodLoadKind::DCEnd => is_new,
+        }
+    }
+}
+
+/// A method that was only listed by the end rundown, i.e. that already existed when the
+/// capture started and was never loaded or unloaded during the capture.
+#[derive(Debug)]
+pub struct RundownMethod {
+    pub name: String,
+    pub namespace: String,
+    pub start_address: u64,
+    pub size: u32,
+}
+
+/// Holds on to the methods listed by the end rundown until the DCEndComplete event tells
+/// us that the rundown is over. Until then we don't know which methods the rundown lists.
+/// For each process, the timestamp of its latest rundown event is kept as well.
+#[derive(Debug, Default)]
+struct PendingRundownMethods {
+    methods: FastHashMap<u32, (u64, Vec<RundownMethod>)>,
+}
+
+impl PendingRundownMethods {
+    fn add(&mut self, pid: u32, timestamp_raw: u64, method: RundownMethod) {
+        let (latest_timestamp_raw, methods) = self.methods.entry(pid).or_default();
+        *latest_timestamp_raw = timestamp_raw;
+        methods.push(method);
+    }
+
+    /// Ends the rundown of a process and returns the methods it listed.
+    fn complete(&mut self, pid: u32) -> Vec<RundownMethod> {
+        self.methods
+            .remove(&pid)
+            .map(|(_, methods)| methods)
+            .unwrap_or_default()
+    }
+
+    /// Ends all rundowns which never got a DCEndComplete event, e.g. because the trace
+    /// was cut off. Returns `(pid, timestamp of the latest rundown event, methods)`,
+    /// ordered by pid.
+    fn complete_all(&mut self) -> Vec<(u32, u64, Vec<RundownMethod>)> {
+        let mut rundowns: Vec<_> = self
+            .methods
+            .drain()
+            .map(|(pid, (timestamp_raw, methods))| (pid, timestamp_raw, methods))
+            .collect();
+        rundowns.sort_unstable_by_key(|(pid, _, _)| *pid);
+        rundowns
+    }
+}
+
+/// Keeps track of the GC handles that are currently alive in each process, based on
+/// SetGCHandle and DestroyGCHandle events.
+#[derive(Debug, Default)]
+struct GcHandleTracker {
+    live_handles: FastHashMap<u32, HashSet<u64>>,
+}
+
+impl GcHandleTracker {
+    /// Records a new handle. Returns false if the handle was already known to be alive.
+    fn set(&mut self, pid: u32, handle_id: u64) -> bool {
+        self.live_handles.entry(pid).or_default().insert(handle_id)
+    }
+
+    /// Records the destruction of a handle. Returns false if the handle wasn't known,
+    /// e.g. because it was created before the recording started.
+    fn destroy(&mut self, pid: u32, handle_id: u64) -> bool {
+        self.live_handles
+            .get_mut(&pid)
+            .is_some_and(|handles| handles.remove(&handle_id))
+    }
+
+    fn live_count(&self, pid: u32) -> usize {
+        self.live_handles.get(&pid).map_or(0, HashSet::len)
+    }
+}
+
+/// Type names from BulkType events, keyed by process and TypeID.
+///
+/// BulkType events are emitted by the runtime (or during rundown, on attach) before
+/// any sampled allocation that refers to a type, so we keep all of them around and
+/// look them up when we see a GCSampledObjectAllocation event.
+#[derive(Debug, Default)]
+struct ClrTypeNames {
+    names: FastHashMap<(u32, u64), String>,
+}
+
+impl ClrTypeNames {
+    /// Adds the types from the "Values" array of a BulkType event.
+    fn add_bulk_type_values(&mut self, pid: u32, count: u32, values: &[u8]) {
+        self.names.reserve(count as usize);
+        for (type_id, name) in BulkTypeValuesIter::new(values).take(count as usize) {
+            self.names.insert((pid, type_id), name);
+        }
+    }
+
+    fn get(&self, pid: u32, type_id: u64) -> Option<&str> {
+        self.names.get(&(pid, type_id)).map(String::as_str)
+    }
+}
+
+/// The maximum number of distinct types whose names we put into the profile's
+/// string table. Past this, allocations of new types use a "(many types)" fallback.
+const MAX_INTERNED_TYPE_NAMES: usize = 100_000;
+
+/// The string handles for the type names used by allocation markers, keyed by
+/// process and TypeID.
+///
+/// A trace with an unbounded number of distinct types (e.g. lots of generic
+/// instantiations created at runtime) would otherwise grow the string table
+/// without limit.
+struct InternedTypeNames {
+    /// The handle, and whether it's for the real name rather than the TypeID.
+    handles: FastHashMap<(u32, u64), (StringHandle, bool)>,
+    max_len: usize,
+    fallback: Option<StringHandle>,
+}
+
+impl InternedTypeNames {
+    fn new(max_len: usize) -> Self {
+        Self {
+            handles: FastHashMap::default(),
+            max_len,
+            fallback: None,
+        }
+    }
+
+    fn get_or_intern(
+        &mut self,
+        pid: u32,
+        type_id: u64,
+        name: Option<&str>,
+        mut intern: impl FnMut(&str) -> StringHandle,
+    ) -> StringHandle {
+        match self.handles.get(&(pid, type_id)) {
+            // Re-intern if the name became known since we used the TypeID.
+            Some(&(handle, has_name)) if has_name || name.is_none() => return handle,
+            Some(_) => {}
+            None if self.handles.len() >= self.max_len => {
+                if self.fallback.is_none() {
+                    eprintln!(
+                        "Warning: More than {} distinct CLR types, \
+                         using \"(many types)\" for the rest",
+                        self.max_len
+                    );
+                    self.fallback = Some(intern("(many types)"));
+                }
+                return self.fallback.unwrap();
+            }
+            None => {}
+        }
+        let has_name = name.is_some();
+        let handle = match name {
+            Some(name) => intern(name),
+            None => intern(&format!("0x{:x}", type_id)),
+        };
+        self.handles.insert((pid, type_id), (handle, has_name));
+        handle
+    }
+}
+
+/// Iterates over the `(TypeID, Name)` pairs in the "Values" array of a BulkType
+/// event, stopping at the first truncated entry.
+struct BulkTypeValuesIter<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> BulkTypeValuesIter<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer }
+    }
+
+    fn take_bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        if self.buffer.len() < N {
+            return None;
+        }
+        let (bytes, rest) = self.buffer.split_at(N);
+        self.buffer = rest;
+        Some(bytes.try_into().unwrap())
+    }
+
+    fn take_utf16_string(&mut self) -> Option<String> {
+        let mut units = Vec::new();
+        loop {
+            match u16::from_le_bytes(self.take_bytes::<2>()?) {
+                0 => break,
+                unit => units.push(unit),
+            }
+        }
+        Some(String::from_utf16_lossy(&units))
+    }
+}
+
+impl Iterator for BulkTypeValuesIter<'_> {
+    type Item = (u64, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let type_id = u64::from_le_bytes(self.take_bytes::<8>()?);
+        let _module_id = self.take_bytes::<8>()?;
+        let _type_name_id = self.take_bytes::<4>()?;
+        let _flags = self.take_bytes::<4>()?;
+        let _cor_element_type = self.take_bytes::<1>()?;
+        let name = self.take_utf16_string()?;
+        let type_parameter_count = u32::from_le_bytes(self.take_bytes::<4>()?) as usize;
+        let type_parameters_len = type_parameter_count.checked_mul(8)?;
+        if self.buffer.len() < type_parameters_len {
+            self.buffer = &[];
+            return None;
+        }
+        self.buffer = &self.buffer[type_parameters_len..];
+        Some((type_id, name))
+    }
+}
+
 pub struct CoreClrContext {
     props: CoreClrProfileProps,
-    last_marker_on_thread: HashMap<u32, (ThreadHandle, MarkerHandle)>,
-    gc_markers_on_thread: HashMap<u32, HashMap<&'static str, SavedMarkerInfo>>,
+    last_marker_on_thread: FastHashMap<u32, (ThreadHandle, MarkerHandle)>,
+    gc_markers_on_thread: FastHashMap<u32, FastHashMap<&'static str, SavedMarkerInfo>>,
+    gc_start_on_thread: FastHashMap<u32, SavedGcStartInfo>,
+    clr_instances: ClrInstanceTracker,
+    type_names: ClrTypeNames,
+    interned_type_names: InternedTypeNames,
+    gc_handles: GcHandleTracker,
+    known_methods: KnownMethods,
+    pending_rundown_methods: PendingRundownMethods,
+    r2r_entry_points_seen: HashSet<(u32, u64)>,
+    loaded_assemblies: HashSet<(u32, u64)>,
+    runtime_info_seen: HashSet<u32>,
     unknown_event_markers: bool,
+    thread_filter: CoreClrThreadFilter,
 }
 
 impl CoreClrContext {
     pub fn new(profile_creation_props: ProfileCreationProps) -> Self {
         Self {
             props: profile_creation_props.coreclr,
-            last_marker_on_thread: HashMap::new(),
-            gc_markers_on_thread: HashMap::new(),
+            last_marker_on_thread: FastHashMap::default(),
+            gc_markers_on_thread: FastHashMap::default(),
+            gc_start_on_thread: FastHashMap::default(),
+            clr_instances: ClrInstanceTracker::default(),
+            type_names: ClrTypeNames::default(),
+            interned_type_names: InternedTypeNames::new(MAX_INTERNED_TYPE_NAMES),
+            gc_handles: GcHandleTracker::default(),
+            known_methods: KnownMethods::default(),
+            pending_rundown_methods: PendingRundownMethods::default(),
+            r2r_entry_points_seen: HashSet::new(),
+            loaded_assemblies: HashSet::new(),
+            runtime_info_seen: HashSet::new(),
             unknown_event_markers: profile_creation_props.unknown_event_markers,
+            thread_filter: profile_creation_props.coreclr_thread_filter,
+        }
+    }
+
+    /// Adds the JIT symbols of end rundowns that didn't finish before the trace ended.
+    pub fn finish(&mut self, context: &mut ProfileContext) {
+        for (pid, timestamp_raw, methods) in self.pending_rundown_methods.complete_all() {
+            context.handle_coreclr_rundown_complete(timestamp_raw, pid, methods);
         }
     }
 
@@ -70,6 +390,42 @@ impl CoreClrContext {
             .get_mut(&tid)
             .and_then(|m| m.remove(event))
     }
+
+    fn gc_category(&self, kind: GcMarkerKind) -> KnownCategory {
+        kind.known_category(self.props.gc_categories)
+    }
+}
+
+/// The kind of a GC-related marker, used to pick the marker's category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GcMarkerKind {
+    Allocation,
+    Pause,
+    /// A GC pause caused by an explicit `GC.Collect()` (or similar) call.
+    InducedPause,
+    Finalizer,
+    Other,
+}
+
+impl GcMarkerKind {
+    /// All GC markers share the "CoreCLR GC" category, unless `separate_categories`
+    /// is set, in which case allocations, pauses and finalizers get their own.
+    /// Induced GCs always get their own category, so that they stand out.
+    fn known_category(self, separate_categories: bool) -> KnownCategory {
+        if self == GcMarkerKind::InducedPause {
+            return KnownCategory::CoreClrGcInduced;
+        }
+        if !separate_categories {
+            return KnownCategory::CoreClrGc;
+        }
+        match self {
+            GcMarkerKind::Allocation => KnownCategory::CoreClrGcAlloc,
+            GcMarkerKind::Pause => KnownCategory::CoreClrGcPause,
+            GcMarkerKind::InducedPause => KnownCategory::CoreClrGcInduced,
+            GcMarkerKind::Finalizer => KnownCategory::CoreClrGcFinalizer,
+            GcMarkerKind::Other => KnownCategory::CoreClrGc,
+        }
+    }
 }
 
 bitflags! {
@@ -106,6 +462,88 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// The keywords of an event from the Microsoft-Windows-DotNETRuntime provider.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CoreClrKeywords: u64 {
+        const GC = constants::CORECLR_GC_KEYWORD;
+        const GCHandle = constants::CORECLR_GC_HANDLE_KEYWORD;
+        const Binder = constants::CORECLR_BINDER_KEYWORD;
+        const Loader = constants::CORECLR_LOADER_KEYWORD;
+        const Jit = constants::CORECLR_JIT_KEYWORD;
+        const NGen = constants::CORECLR_NGEN_KEYWORD;
+        const StartEnumeration = constants::CORECLR_RUNDOWN_START_KEYWORD;
+        const EndEnumeration = constants::CORECLR_RUNDOWN_END_KEYWORD;
+        const Interop = constants::CORECLR_INTEROP_KEYWORD;
+        const Contention = constants::CORECLR_CONTENTION_KEYWORD;
+        const Exception = constants::CORECLR_EXCEPTION_KEYWORD;
+        const Threading = constants::CORECLR_THREADING_KEYWORD;
+        const JittedMethodILToNativeMap = constants::CORECLR_JIT_TO_NATIVE_METHOD_MAP_KEYWORD;
+        const Type = constants::CORECLR_TYPE_KEYWORD;
+        const GCSampledObjectAllocationHigh = constants::CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_HIGH_KEYWORD;
+        const GCHeapAndTypeNames = constants::CORECLR_GC_HEAP_AND_TYPE_NAMES;
+        const GCSampledObjectAllocationLow = constants::CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_LOW_KEYWORD;
+        const Stack = constants::CORECLR_STACK_KEYWORD;
+        const Compilation = constants::CORECLR_COMPILATION_KEYWORD;
+        const CompilationDiagnostic = constants::CORECLR_COMPILATION_DIAGNOSTIC_KEYWORD;
+        const TypeDiagnostic = constants::CORECLR_TYPE_DIAGNOSTIC_KEYWORD;
+    }
+}
+
+impl Display for CoreClrKeywords {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("None");
+        }
+        let mut first = true;
+        for (name, _) in self.iter_names() {
+            if !first {
+                f.write_str(", ")?;
+            }
+            f.write_str(name)?;
+            first = false;
+        }
+        let unknown_bits = self.bits() & !Self::all().bits();
+        if unknown_bits != 0 {
+            if !first {
+                f.write_str(", ")?;
+            }
+            write!(f, "0x{unknown_bits:x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether an event comes from one of the CoreCLR providers that
+/// `handle_coreclr_event` understands. Events from other providers, including other
+/// DotNETRuntime* providers added with `--coreclr-provider`, are treated as unknown events.
+pub fn is_coreclr_event(event_name: &str) -> bool {
+    let provider = event_name.split('/').next().unwrap_or_default();
+    matches!(
+        provider,
+        "Microsoft-Windows-DotNETRuntime" | "Microsoft-Windows-DotNETRuntimeRundown"
+    )
+}
+
+/// Returns whether an event is part of a rundown, i.e. an enumeration of the methods,
+/// modules and assemblies that are loaded when the rundown happens.
+fn is_rundown_event(provider: &str, opcode: &str) -> bool {
+    provider == "Microsoft-Windows-DotNETRuntimeRundown"
+        || opcode.contains("DCStart")
+        || opcode.contains("DCEnd")
+}
+
+/// Returns a readable description of an event's keywords. The keyword names are only
+/// known for the runtime provider; for other providers, the raw bits are returned.
+fn coreclr_keywords_description(provider: &str, keywords: u64) -> String {
+    match provider {
+        "Microsoft-Windows-DotNETRuntime" => {
+            CoreClrKeywords::from_bits_retain(keywords).to_string()
+        }
+        _ => format!("0x{keywords:x}"),
+    }
+}
+
 #[allow(unused)]
 mod constants {
     pub const CORECLR_GC_KEYWORD: u64 = 0x1; // https://learn.microsoft.com/en-us/dotnet/fundamentals/diagnostics/runtime-garbage-collection-events
@@ -115,11 +553,13 @@ mod constants {
     pub const CORECLR_JIT_KEYWORD: u64 = 0x10; // https://learn.microsoft.com/en-us/dotnet/fundamentals/diagnostics/runtime-method-events
     pub const CORECLR_NGEN_KEYWORD: u64 = 0x20; // https://learn.microsoft.com/en-us/dotnet/fundamentals/diagnostics/runtime-method-events
     pub const CORECLR_RUNDOWN_START_KEYWORD: u64 = 0x00000040;
+    pub const CORECLR_RUNDOWN_END_KEYWORD: u64 = 0x00000100;
     pub const CORECLR_INTEROP_KEYWORD: u64 = 0x2000; // https://learn.microsoft.com/en-us/dotnet/fundamentals/diagnostics/runtime-interop-events
     pub const CORECLR_CONTENTION_KEYWORD: u64 = 0x4000;
     pub const CORECLR_EXCEPTION_KEYWORD: u64 = 0x8000; // https://learn.microsoft.com/en-us/dotnet/fundamentals/diagnostics/runtime-exception-events
     pub const CORECLR_THREADING_KEYWORD: u64 = 0x10000; // https://learn.microsoft.com/en-us/dotnet/fundamentals/diagnostics/runtime-thread-events
     pub const CORECLR_JIT_TO_NATIVE_METHOD_MAP_KEYWORD: u64 = 0x20000;
+    pub const CORECLR_TYPE_KEYWORD: u64 = 0x80000;
     pub const CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_HIGH_KEYWORD: u64 = 0x200000; // https://medium.com/criteo-engineering/build-your-own-net-memory-profiler-in-c-allocations-1-2-9c9f0c86cefd
     pub const CORECLR_GC_HEAP_AND_TYPE_NAMES: u64 = 0x1000000;
     pub const CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_LOW_KEYWORD: u64 = 0x2000000;
@@ -129,9 +569,9 @@ mod constants {
     pub const CORECLR_TYPE_DIAGNOSTIC_KEYWORD: u64 = 0x8000000000;
 }
 
-#[derive(Debug, Clone, FromPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum GcReason {
-    AllocSmall = 0,
+    AllocSmall,
     Induced,
     LowMemory,
     Empty,
@@ -141,6 +581,43 @@ enum GcReason {
     InducedNoForce,
     Stress,
     InducedLowMemory,
+    /// A reason added by a newer runtime. The raw value is kept so that it can be displayed.
+    Unknown(u32),
+}
+
+impl GcReason {
+    fn from_raw(reason: u32) -> Self {
+        match reason {
+            0 => GcReason::AllocSmall,
+            1 => GcReason::Induced,
+            2 => GcReason::LowMemory,
+            3 => GcReason::Empty,
+            4 => GcReason::AllocLarge,
+            5 => GcReason::OutOfSpaceSmallObjectHeap,
+            6 => GcReason::OutOfSpaceLargeObjectHeap,
+            7 => GcReason::InducedNoForce,
+            8 => GcReason::Stress,
+            9 => GcReason::InducedLowMemory,
+            other => GcReason::Unknown(other),
+        }
+    }
+
+    /// Whether this GC was requested explicitly, usually by a `GC.Collect()` call.
+    fn is_induced(&self) -> bool {
+        matches!(
+            self,
+            GcReason::Induced | GcReason::InducedNoForce | GcReason::InducedLowMemory
+        )
+    }
+
+    /// The marker kind for a GC with this reason.
+    fn pause_marker_kind(&self) -> GcMarkerKind {
+        if self.is_induced() {
+            GcMarkerKind::InducedPause
+        } else {
+            GcMarkerKind::Pause
+        }
+    }
 }
 
 impl Display for GcReason {
@@ -160,13 +637,14 @@ impl Display for GcReason {
             GcReason::InducedNoForce => f.write_str("Induced but not forced as blocking"),
             GcReason::Stress => f.write_str("Stress"),
             GcReason::InducedLowMemory => f.write_str("Induced low memory"),
+            GcReason::Unknown(reason) => write!(f, "Unknown ({reason})"),
         }
     }
 }
 
-#[derive(Debug, Clone, FromPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum GcSuspendEeReason {
-    Other = 0,
+    Other,
     GC,
     AppDomainShutdown,
     CodePitching,
@@ -174,6 +652,24 @@ enum GcSuspendEeReason {
     Debugger,
     GcPrep,
     DebuggerSweep,
+    /// A reason added by a newer runtime.
+    Unknown(u32),
+}
+
+impl GcSuspendEeReason {
+    fn from_raw(reason: u32) -> Self {
+        match reason {
+            0 => GcSuspendEeReason::Other,
+            1 => GcSuspendEeReason::GC,
+            2 => GcSuspendEeReason::AppDomainShutdown,
+            3 => GcSuspendEeReason::CodePitching,
+            4 => GcSuspendEeReason::Shutdown,
+            5 => GcSuspendEeReason::Debugger,
+            6 => GcSuspendEeReason::GcPrep,
+            7 => GcSuspendEeReason::DebuggerSweep,
+            other => GcSuspendEeReason::Unknown(other),
+        }
+    }
 }
 
 impl Display for GcSuspendEeReason {
@@ -187,15 +683,46 @@ impl Display for GcSuspendEeReason {
             GcSuspendEeReason::Debugger => f.write_str("Debugger"),
             GcSuspendEeReason::GcPrep => f.write_str("GC prep"),
             GcSuspendEeReason::DebuggerSweep => f.write_str("Debugger sweep"),
+            GcSuspendEeReason::Unknown(reason) => write!(f, "Unknown ({reason})"),
         }
     }
 }
 
-#[derive(Debug, Clone, FromPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum GcType {
     Blocking,
     Background,
     BlockingDuringBackground,
+    /// A GC type added by a newer runtime.
+    Unknown(u32),
+}
+
+impl GcType {
+    fn from_raw(gc_type: u32) -> Self {
+        match gc_type {
+            0 => GcType::Blocking,
+            1 => GcType::Background,
+            2 => GcType::BlockingDuringBackground,
+            other => GcType::Unknown(other),
+        }
+    }
+
+    /// Whether the app's threads are paused for the whole GC, if known.
+    fn is_blocking(&self) -> Option<bool> {
+        match self {
+            GcType::Blocking | GcType::BlockingDuringBackground => Some(true),
+            GcType::Background => Some(false),
+            GcType::Unknown(_) => None,
+        }
+    }
+
+    fn pause_kind(&self) -> &'static str {
+        match self.is_blocking() {
+            Some(true) => "Blocking",
+            Some(false) => "Background",
+            None => "Unknown",
+        }
+    }
 }
 
 impl Display for GcType {
@@ -204,12 +731,149 @@ impl Display for GcType {
             GcType::Blocking => f.write_str("Blocking GC"),
             GcType::Background => f.write_str("Background GC"),
             GcType::BlockingDuringBackground => f.write_str("Blocking GC during background GC"),
+            GcType::Unknown(gc_type) => write!(f, "Unknown GC type ({gc_type})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GcHandleKind {
+    WeakShort,
+    WeakLong,
+    Strong,
+    Pinned,
+    Variable,
+    RefCounted,
+    Dependent,
+    AsyncPinned,
+    SizedRef,
+    WeakWinRT,
+    /// A handle kind added by a newer runtime.
+    Unknown(u32),
+}
+
+impl GcHandleKind {
+    fn from_raw(kind: u32) -> Self {
+        match kind {
+            0 => GcHandleKind::WeakShort,
+            1 => GcHandleKind::WeakLong,
+            2 => GcHandleKind::Strong,
+            3 => GcHandleKind::Pinned,
+            4 => GcHandleKind::Variable,
+            5 => GcHandleKind::RefCounted,
+            6 => GcHandleKind::Dependent,
+            7 => GcHandleKind::AsyncPinned,
+            8 => GcHandleKind::SizedRef,
+            9 => GcHandleKind::WeakWinRT,
+            other => GcHandleKind::Unknown(other),
+        }
+    }
+}
+
+impl Display for GcHandleKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GcHandleKind::WeakShort => f.write_str("Weak (short)"),
+            GcHandleKind::WeakLong => f.write_str("Weak (long)"),
+            GcHandleKind::Strong => f.write_str("Strong"),
+            GcHandleKind::Pinned => f.write_str("Pinned"),
+            GcHandleKind::Variable => f.write_str("Variable"),
+            GcHandleKind::RefCounted => f.write_str("Ref-counted"),
+            GcHandleKind::Dependent => f.write_str("Dependent"),
+            GcHandleKind::AsyncPinned => f.write_str("Async pinned"),
+            GcHandleKind::SizedRef => f.write_str("Sized ref"),
+            GcHandleKind::WeakWinRT => f.write_str("Weak (WinRT)"),
+            GcHandleKind::Unknown(kind) => write!(f, "Unknown ({kind})"),
+        }
+    }
+}
+
+bitflags! {
+    /// The `GlobalMechanisms` field of GCGlobalHeapHistory.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct GcGlobalMechanisms: u32 {
+        const Concurrent = 0x1;
+        const Compaction = 0x2;
+        const Promotion = 0x4;
+        const Demotion = 0x8;
+        const CardBundles = 0x10;
+        const Elevation = 0x20;
+    }
+}
+
+impl Display for GcGlobalMechanisms {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("None");
+        }
+        for (i, (name, _)) in self.iter_names().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// GCGlobalHeapHistory summarizes the decisions the GC made for the whole heap
+/// (across all heaps, for server GC) at the end of a GC.
+#[derive(Debug, Clone)]
+struct GcGlobalHeapHistoryEvent {
+    final_youngest_desired: u64,
+    num_heaps: i32,
+    condemned_generation: u32,
+    gen0_reduction_count: u32,
+    reason: GcReason,
+    global_mechanisms: GcGlobalMechanisms,
+    /// The raw CondemnReasons0 / CondemnReasons1 bitfields. Only present in V3+ of the event.
+    condemn_reasons: Option<(u32, u32)>,
+}
+
+impl GcGlobalHeapHistoryEvent {
+    fn parse(parser: &mut Parser) -> Self {
+        let final_youngest_desired: u64 = parser.parse("FinalYoungestDesired");
+        let num_heaps: i32 = parser.parse("NumHeaps");
+        let condemned_generation: u32 = parser.parse("CondemnedGeneration");
+        let gen0_reduction_count: u32 = parser.parse("Gen0ReductionCount");
+        let reason: u32 = parser.parse("Reason");
+        let global_mechanisms: u32 = parser.parse("GlobalMechanisms");
+        let condemn_reasons0: Option<u32> = parser.try_parse("CondemnReasons0").ok();
+        let condemn_reasons1: Option<u32> = parser.try_parse("CondemnReasons1").ok();
+        Self {
+            final_youngest_desired,
+            num_heaps,
+            condemned_generation,
+            gen0_reduction_count,
+            reason: GcReason::from_raw(reason),
+            global_mechanisms: GcGlobalMechanisms::from_bits_retain(global_mechanisms),
+            condemn_reasons: condemn_reasons0.zip(condemn_reasons1),
         }
     }
 }
+
+impl Display for GcGlobalHeapHistoryEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "gen{} ({}), {} heap(s), mechanisms: {}, final youngest desired: {} bytes, gen0 reductions: {}",
+            self.condemned_generation,
+            self.reason,
+            self.num_heaps,
+            self.global_mechanisms,
+            self.final_youngest_desired,
+            self.gen0_reduction_count
+        )?;
+        if let Some((reasons0, reasons1)) = self.condemn_reasons {
+            write!(f, ", condemn reasons: 0x{reasons0:x} 0x{reasons1:x}")?;
+        }
+        Ok(())
+    }
+}
+
 // String is type name
 #[derive(Debug, Clone)]
-pub struct CoreClrGcAllocMarker(StringHandle, f64, CategoryHandle);
+pub struct CoreClrGcAllocMarker(StringHandle, f64, StringHandle, CategoryHandle);
 
 impl StaticSchemaMarker for CoreClrGcAllocMarker {
     const UNIQUE_MARKER_TYPE_NAME: &'static str = "GC Alloc";
@@ -240,6 +904,12 @@ impl StaticSchemaMarker for CoreClrGcAllocMarker {
                     format: MarkerFieldFormat::Bytes,
                     searchable: false,
                 },
+                MarkerFieldSchema {
+                    key: "heap".into(),
+                    label: "Heap".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
             ],
             static_fields: vec![MarkerStaticField {
                 label: "Description".into(),
@@ -253,11 +923,15 @@ impl StaticSchemaMarker for CoreClrGcAllocMarker {
     }
 
     fn category(&self, _profile: &mut Profile) -> CategoryHandle {
-        self.2
+        self.3
     }
 
-    fn string_field_value(&self, _field_index: u32) -> StringHandle {
-        self.0
+    fn string_field_value(&self, field_index: u32) -> StringHandle {
+        match field_index {
+            0 => self.0,
+            2 => self.2,
+            _ => unreachable!(),
+        }
     }
 
     fn number_field_value(&self, _field_index: u32) -> f64 {
@@ -265,6 +939,39 @@ impl StaticSchemaMarker for CoreClrGcAllocMarker {
     }
 }
 
+/// The GC heap that an allocation went to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GcHeap {
+    SmallObject,
+    LargeObject,
+}
+
+impl GcHeap {
+    /// Objects of at least this size are allocated on the large object heap.
+    const LARGE_OBJECT_THRESHOLD: u64 = 85_000;
+
+    /// GCSampledObjectAllocation events don't say which heap was used, and for sampled
+    /// allocations the size is the total for all objects of the type since the last
+    /// sample, so we go by the average object size.
+    fn infer(object_count: u32, total_size: u64) -> Self {
+        let object_size = total_size / u64::from(object_count.max(1));
+        if object_size >= Self::LARGE_OBJECT_THRESHOLD {
+            GcHeap::LargeObject
+        } else {
+            GcHeap::SmallObject
+        }
+    }
+}
+
+impl Display for GcHeap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GcHeap::SmallObject => f.write_str("SOH"),
+            GcHeap::LargeObject => f.write_str("LOH"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CoreClrGcEventMarker(StringHandle, StringHandle, CategoryHandle);
 
@@ -312,60 +1019,615 @@ impl StaticSchemaMarker for CoreClrGcEventMarker {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct DisplayUnknownIfNone<'a, T>(pub &'a Option<T>);
+/// An interval marker for the time the runtime is suspended, from GCSuspendEEBegin to
+/// GCRestartEEEnd. The runtime isn't only suspended for GCs, so the reason is a
+/// separate field.
+#[derive(Debug, Clone)]
+pub struct CoreClrGcSuspensionMarker {
+    name: StringHandle,
+    reason: StringHandle,
+    category: CategoryHandle,
+}
 
-impl<'a, T: Display> Display for DisplayUnknownIfNone<'a, T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.0 {
-            Some(value) => value.fmt(f),
-            None => f.write_str("Unknown"),
+impl StaticSchemaMarker for CoreClrGcSuspensionMarker {
+    const UNIQUE_MARKER_TYPE_NAME: &'static str = "CoreClrGcSuspension";
+
+    fn schema() -> MarkerSchema {
+        MarkerSchema {
+            type_name: Self::UNIQUE_MARKER_TYPE_NAME.into(),
+            locations: vec![
+                MarkerLocation::MarkerChart,
+                MarkerLocation::MarkerTable,
+                MarkerLocation::TimelineMemory,
+            ],
+            chart_label: Some("Suspended: {marker.data.reason}".into()),
+            tooltip_label: Some("Suspended: {marker.data.reason}".into()),
+            table_label: Some("Suspended: {marker.data.reason}".into()),
+            fields: vec![MarkerFieldSchema {
+                key: "reason".into(),
+                label: "Reason".into(),
+                format: MarkerFieldFormat::String,
+                searchable: true,
+            }],
+            static_fields: vec![MarkerStaticField {
+                label: "Description".into(),
+                value: "The runtime suspended managed threads, e.g. for a GC or the debugger."
+                    .into(),
+            }],
         }
     }
-}
 
-pub fn coreclr_xperf_args(props: &ElevatedRecordingProps) -> Vec<String> {
-    let mut providers = vec![];
+    fn name(&self, _profile: &mut Profile) -> StringHandle {
+        self.name
+    }
 
-    if !props.coreclr.any_enabled() {
-        return providers;
+    fn category(&self, _profile: &mut Profile) -> CategoryHandle {
+        self.category
     }
 
-    // Enabling all the DotNETRuntime keywords is very expensive. In particular,
-    // enabling the NGenKeyword causes info to be generated for every NGen'd method; we should
-    // instead use the native PDB info from ModuleLoad events to get this information.
-    //
-    // Also enabling the rundown keyword causes a bunch of DCStart/DCEnd events to be generated,
-    // which is only useful if we're tracing an already running process.
-    // if STACK is enabled, then every CoreCLR event will also generate a stack event right afterwards
-    use constants::*;
-    let mut info_keywords = CORECLR_LOADER_KEYWORD;
-    if props.coreclr.event_stacks {
-        info_keywords |= CORECLR_STACK_KEYWORD;
+    fn string_field_value(&self, _field_index: u32) -> StringHandle {
+        self.reason
     }
-    if props.coreclr.gc_markers || props.coreclr.gc_suspensions || props.coreclr.gc_detailed_allocs
-    {
-        info_keywords |= CORECLR_GC_KEYWORD;
+
+    fn number_field_value(&self, _field_index: u32) -> f64 {
+        unreachable!()
     }
+}
 
-    let verbose_keywords = CORECLR_JIT_KEYWORD | CORECLR_NGEN_KEYWORD;
+/// An interval marker for a single GC, from GCStart to GCEnd. The condemned generation,
+/// the reason and the GC type are separate fields so that GCs can be filtered and sorted
+/// by them in the marker table.
+#[derive(Debug, Clone)]
+pub struct CoreClrGcDetailedMarker {
+    count: u32,
+    generation: u32,
+    reason: StringHandle,
+    gc_type: StringHandle,
+    /// "Blocking" or "Background", so that GCs can be filtered by whether they paused the app.
+    pause_kind: StringHandle,
+    pause_ms: f64,
+    category: CategoryHandle,
+}
 
-    // if we're attaching, ask for a rundown of method info at the start of collection
-    let rundown_verbose_keywords = if props.is_attach {
-        CORECLR_LOADER_KEYWORD | CORECLR_JIT_KEYWORD | CORECLR_RUNDOWN_START_KEYWORD
-    } else {
-        0
-    };
+impl StaticSchemaMarker for CoreClrGcDetailedMarker {
+    const UNIQUE_MARKER_TYPE_NAME: &'static str = "CoreClrGc";
+
+    fn schema() -> MarkerSchema {
+        MarkerSchema {
+            type_name: Self::UNIQUE_MARKER_TYPE_NAME.into(),
+            locations: vec![
+                MarkerLocation::MarkerChart,
+                MarkerLocation::MarkerTable,
+                MarkerLocation::TimelineMemory,
+            ],
+            chart_label: Some("GC gen{marker.data.generation}".into()),
+            tooltip_label: Some(
+                "{marker.data.type}: {marker.data.reason} (GC #{marker.data.count}, gen{marker.data.generation})".into(),
+            ),
+            table_label: Some(
+                "{marker.data.type}: {marker.data.reason} (GC #{marker.data.count}, gen{marker.data.generation})".into(),
+            ),
+            fields: vec![
+                MarkerFieldSchema {
+                    key: "count".into(),
+                    label: "GC Number".into(),
+                    format: MarkerFieldFormat::Integer,
+                    searchable: false,
+                },
+                MarkerFieldSchema {
+                    key: "generation".into(),
+                    label: "Generation".into(),
+                    format: MarkerFieldFormat::Integer,
+                    searchable: false,
+                },
+                MarkerFieldSchema {
+                    key: "reason".into(),
+                    label: "Reason".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+                MarkerFieldSchema {
+                    key: "type".into(),
+                    label: "Type".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+                MarkerFieldSchema {
+                    key: "pauseKind".into(),
+                    label: "Pause".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+                MarkerFieldSchema {
+                    key: "pauseDuration".into(),
+                    label: "Pause duration".into(),
+                    format: MarkerFieldFormat::Milliseconds,
+                    searchable: false,
+                },
+            ],
+            static_fields: vec![MarkerStaticField {
+                label: "Description".into(),
+                value: "Garbage collection, from GC start to GC end.".into(),
+            }],
+        }
+    }
+
+    fn name(&self, profile: &mut Profile) -> StringHandle {
+        profile.intern_string("GC")
+    }
+
+    fn category(&self, _profile: &mut Profile) -> CategoryHandle {
+        self.category
+    }
+
+    fn string_field_value(&self, field_index: u32) -> StringHandle {
+        match field_index {
+            2 => self.reason,
+            3 => self.gc_type,
+            4 => self.pause_kind,
+            _ => unreachable!(),
+        }
+    }
+
+    fn number_field_value(&self, field_index: u32) -> f64 {
+        match field_index {
+            0 => self.count.into(),
+            1 => self.generation.into(),
+            5 => self.pause_ms,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// An instant marker for the first time a ReadyToRun (precompiled) method's entry
+/// point is looked up, i.e. when the method starts being used.
+#[derive(Debug, Clone)]
+pub struct CoreClrR2rEntryPointMarker(StringHandle, CategoryHandle);
+
+impl StaticSchemaMarker for CoreClrR2rEntryPointMarker {
+    const UNIQUE_MARKER_TYPE_NAME: &'static str = "CoreClrR2rEntryPoint";
+
+    fn schema() -> MarkerSchema {
+        MarkerSchema {
+            type_name: Self::UNIQUE_MARKER_TYPE_NAME.into(),
+            locations: vec![MarkerLocation::MarkerChart, MarkerLocation::MarkerTable],
+            chart_label: Some("{marker.data.method}".into()),
+            tooltip_label: Some("R2R entry point: {marker.data.method}".into()),
+            table_label: Some("R2R entry point: {marker.data.method}".into()),
+            fields: vec![MarkerFieldSchema {
+                key: "method".into(),
+                label: "Method".into(),
+                format: MarkerFieldFormat::String,
+                searchable: true,
+            }],
+            static_fields: vec![MarkerStaticField {
+                label: "Description".into(),
+                value: "A ReadyToRun method was used for the first time.".into(),
+            }],
+        }
+    }
+
+    fn name(&self, profile: &mut Profile) -> StringHandle {
+        profile.intern_string("R2R Entry Point")
+    }
+
+    fn category(&self, _profile: &mut Profile) -> CategoryHandle {
+        self.1
+    }
+
+    fn string_field_value(&self, _field_index: u32) -> StringHandle {
+        self.0
+    }
+
+    fn number_field_value(&self, _field_index: u32) -> f64 {
+        unreachable!()
+    }
+}
+
+/// Well-known runtime threads which we can recognize from the events they emit.
+///
+/// Thread pool workers could be recognized from ThreadPoolWorkerThread events, but
+/// those need the Threading keyword, which we don't enable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ManagedThreadKind {
+    Finalizer,
+}
+
+impl ManagedThreadKind {
+    /// Returns the kind of thread that emitted an event with this task and opcode,
+    /// if the event is only ever emitted on one kind of thread.
+    fn from_event(task: &str, opcode: &str) -> Option<Self> {
+        match (task, opcode) {
+            ("GarbageCollection", "GCFinalizersBegin" | "GCFinalizersEnd") => {
+                Some(ManagedThreadKind::Finalizer)
+            }
+            _ => None,
+        }
+    }
+
+    fn thread_name(self) -> &'static str {
+        match self {
+            ManagedThreadKind::Finalizer => "Finalizer",
+        }
+    }
+}
+
+/// An instant marker for an assembly load or unload.
+#[derive(Debug, Clone)]
+pub struct CoreClrAssemblyMarker {
+    name: StringHandle,
+    short_name: StringHandle,
+    full_name: StringHandle,
+    category: CategoryHandle,
+}
+
+impl StaticSchemaMarker for CoreClrAssemblyMarker {
+    const UNIQUE_MARKER_TYPE_NAME: &'static str = "CoreClrAssembly";
+
+    fn schema() -> MarkerSchema {
+        MarkerSchema {
+            type_name: Self::UNIQUE_MARKER_TYPE_NAME.into(),
+            locations: vec![MarkerLocation::MarkerChart, MarkerLocation::MarkerTable],
+            chart_label: Some("{marker.data.assembly}".into()),
+            tooltip_label: Some("{marker.name}: {marker.data.assembly}".into()),
+            table_label: Some("{marker.name}: {marker.data.fullName}".into()),
+            fields: vec![
+                MarkerFieldSchema {
+                    key: "assembly".into(),
+                    label: "Assembly".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+                MarkerFieldSchema {
+                    key: "fullName".into(),
+                    label: "Full name".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+            ],
+            static_fields: vec![MarkerStaticField {
+                label: "Description".into(),
+                value: "A managed assembly was loaded or unloaded.".into(),
+            }],
+        }
+    }
+
+    fn name(&self, _profile: &mut Profile) -> StringHandle {
+        self.name
+    }
+
+    fn category(&self, _profile: &mut Profile) -> CategoryHandle {
+        self.category
+    }
+
+    fn string_field_value(&self, field_index: u32) -> StringHandle {
+        match field_index {
+            0 => self.short_name,
+            1 => self.full_name,
+            _ => unreachable!(),
+        }
+    }
+
+    fn number_field_value(&self, _field_index: u32) -> f64 {
+        unreachable!()
+    }
+}
+
+/// A GCDynamicEvent: a named GC event with an opaque payload. Newer runtimes use it
+/// for GC telemetry that isn't in the event manifest, e.g. "SizeAdaptationTuning".
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GcDynamicEvent {
+    name: String,
+    payload: Vec<u8>,
+}
+
+impl GcDynamicEvent {
+    /// The number of payload bytes to show in the marker description.
+    const MAX_DISPLAYED_BYTES: usize = 64;
+
+    fn description(&self) -> String {
+        let mut s = format!("{}: {} bytes", self.name, self.payload.len());
+        if !self.payload.is_empty() {
+            s.push_str(", ");
+            for byte in self.payload.iter().take(Self::MAX_DISPLAYED_BYTES) {
+                s.push_str(&format!("{byte:02x}"));
+            }
+            if self.payload.len() > Self::MAX_DISPLAYED_BYTES {
+                s.push_str("...");
+            }
+        }
+        s
+    }
+}
+
+/// The fields of the RuntimeInformationStart event, which the runtime logs once when
+/// it starts up (and again as RuntimeInformationDCStart during rundown).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RuntimeInformationEvent {
+    /// 1 for the .NET Framework desktop CLR, 2 for CoreCLR.
+    sku: u16,
+    /// The runtime (VM) version. The event also has the base class library version,
+    /// which is the same for .NET Core and later.
+    vm_version: [u16; 4],
+    command_line: String,
+    runtime_dll_path: String,
+}
+
+impl RuntimeInformationEvent {
+    fn parse(parser: &mut Parser) -> Self {
+        let vm_version = ["MajorVersion", "MinorVersion", "BuildNumber", "QfeNumber"]
+            .map(|part| parser.try_parse(&format!("VM{part}")).unwrap_or(0));
+        Self {
+            sku: parser.try_parse("Sku").unwrap_or(0),
+            vm_version,
+            command_line: parser.try_parse("CommandLine").unwrap_or_default(),
+            runtime_dll_path: parser.try_parse("RuntimeDllPath").unwrap_or_default(),
+        }
+    }
+
+    fn runtime_name(&self) -> &'static str {
+        match self.sku {
+            1 => ".NET Framework",
+            2 => ".NET",
+            _ => "Unknown CLR",
+        }
+    }
+
+    /// A label like ".NET 8.0.324.11423".
+    fn version_label(&self) -> String {
+        let [major, minor, build, qfe] = self.vm_version;
+        format!("{} {major}.{minor}.{build}.{qfe}", self.runtime_name())
+    }
+}
+
+/// An instant marker with the runtime version, from the RuntimeInformation event.
+#[derive(Debug, Clone)]
+pub struct CoreClrRuntimeInformationMarker {
+    version: StringHandle,
+    command_line: StringHandle,
+    runtime_dll_path: StringHandle,
+    category: CategoryHandle,
+}
+
+impl StaticSchemaMarker for CoreClrRuntimeInformationMarker {
+    const UNIQUE_MARKER_TYPE_NAME: &'static str = "CoreClrRuntimeInformation";
+
+    fn schema() -> MarkerSchema {
+        MarkerSchema {
+            type_name: Self::UNIQUE_MARKER_TYPE_NAME.into(),
+            locations: vec![MarkerLocation::MarkerChart, MarkerLocation::MarkerTable],
+            chart_label: Some("{marker.data.version}".into()),
+            tooltip_label: Some("{marker.data.version}".into()),
+            table_label: Some("{marker.data.version}: {marker.data.commandLine}".into()),
+            fields: vec![
+                MarkerFieldSchema {
+                    key: "version".into(),
+                    label: "Version".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+                MarkerFieldSchema {
+                    key: "commandLine".into(),
+                    label: "Command line".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+                MarkerFieldSchema {
+                    key: "runtimePath".into(),
+                    label: "Runtime path".into(),
+                    format: MarkerFieldFormat::FilePath,
+                    searchable: false,
+                },
+            ],
+            static_fields: vec![MarkerStaticField {
+                label: "Description".into(),
+                value: "The version of the .NET runtime in this process.".into(),
+            }],
+        }
+    }
+
+    fn name(&self, profile: &mut Profile) -> StringHandle {
+        profile.intern_string("CLR Runtime")
+    }
+
+    fn category(&self, _profile: &mut Profile) -> CategoryHandle {
+        self.category
+    }
+
+    fn string_field_value(&self, field_index: u32) -> StringHandle {
+        match field_index {
+            0 => self.version,
+            1 => self.command_line,
+            2 => self.runtime_dll_path,
+            _ => unreachable!(),
+        }
+    }
+
+    fn number_field_value(&self, _field_index: u32) -> f64 {
+        unreachable!()
+    }
+}
+
+/// Returns the simple name of an assembly from its fully qualified name, e.g.
+/// "Newtonsoft.Json" for "Newtonsoft.Json, Version=13.0.0.0, Culture=neutral, ...".
+fn assembly_short_name(fully_qualified_name: &str) -> &str {
+    match fully_qualified_name.split_once(',') {
+        Some((name, _)) => name.trim(),
+        None => fully_qualified_name.trim(),
+    }
+}
+
+/// Formats a method name from the name parts in CLRMethod events.
+fn coreclr_method_name(basename: &str, namespace: &str, signature: &str) -> String {
+    format!("{basename} [{namespace}] \u{2329}{signature}\u{232a}")
+}
+
+/// A name for a method whose MethodLoad event has no name parts at all. We use the
+/// module and the method token if we have them, so that the method can at least be
+/// told apart from other nameless methods.
+fn coreclr_fallback_method_name(module_id: u64, method_token: u32, start_address: u64) -> String {
+    if module_id == 0 && method_token == 0 {
+        format!("JIT[0x{start_address:x}]")
+    } else {
+        format!("DynamicMethod@Module0x{module_id:x}+0x{method_token:x}")
+    }
+}
+
+/// A rough estimate of how much a set of CoreCLR flags slows down the profiled process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CoreClrOverhead {
+    Low,
+    Moderate,
+    High,
+    VeryHigh,
+}
+
+impl Display for CoreClrOverhead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoreClrOverhead::Low => f.write_str("low"),
+            CoreClrOverhead::Moderate => f.write_str("moderate"),
+            CoreClrOverhead::High => f.write_str("high"),
+            CoreClrOverhead::VeryHigh => f.write_str("very high"),
+        }
+    }
+}
+
+/// Estimates the recording overhead of the given CoreCLR flags. Returns the overall
+/// estimate and the flags that contribute more than a low overhead, with the reason.
+/// Extra providers aren't taken into account, since we don't know what they log.
+pub fn coreclr_overhead_estimate(
+    props: &CoreClrProfileProps,
+) -> (CoreClrOverhead, Vec<(CoreClrOverhead, &'static str)>) {
+    let mut reasons = vec![];
+    if props.gc_detailed_allocs {
+        reasons.push(match props.gc_alloc_sampling {
+            CoreClrAllocSampling::Low => (
+                CoreClrOverhead::Moderate,
+                "gc-detailed-allocs: an event every ~100KB of allocations",
+            ),
+            CoreClrAllocSampling::High | CoreClrAllocSampling::Both => (
+                CoreClrOverhead::VeryHigh,
+                "gc-detailed-allocs-high: an event for almost every allocation",
+            ),
+        });
+    }
+    if props.event_stacks {
+        reasons.push((
+            CoreClrOverhead::High,
+            "event-stacks: a stack walk for every CoreCLR event",
+        ));
+    }
+    if props.gc_handles {
+        reasons.push((
+            CoreClrOverhead::Moderate,
+            "gc-handles: an event for every GC handle creation and destruction",
+        ));
+    }
+    if props.r2r_markers {
+        reasons.push((
+            CoreClrOverhead::Moderate,
+            "r2r-markers: an event the first time each precompiled method is used",
+        ));
+    }
+    if props.resolve_type_names {
+        reasons.push((
+            CoreClrOverhead::Moderate,
+            "type-names: a BulkType event for each type",
+        ));
+    }
+    if props.end_rundown {
+        reasons.push((
+            CoreClrOverhead::Moderate,
+            "end-rundown: a second method rundown when attaching",
+        ));
+    }
+    let overall = reasons
+        .iter()
+        .map(|(overhead, _)| *overhead)
+        .max()
+        .unwrap_or(CoreClrOverhead::Low);
+    (overall, reasons)
+}
+
+pub fn coreclr_xperf_args(props: &ElevatedRecordingProps) -> Vec<String> {
+    let mut providers = coreclr_providers(props);
+    merge_extra_providers(&mut providers, &props.coreclr.extra_providers);
+    providers
+        .into_iter()
+        .map(|(provider, keywords, level)| format!("{provider}:0x{keywords:x}:{level}"))
+        .collect()
+}
+
+/// Returns the `(provider, keywords, level)` entries for the CoreCLR providers
+/// we need for the given recording props.
+fn coreclr_providers(props: &ElevatedRecordingProps) -> Vec<(String, u64, u8)> {
+    let mut providers = vec![];
+
+    if !props.coreclr.any_enabled() {
+        return providers;
+    }
+
+    // Enabling all the DotNETRuntime keywords is very expensive. In particular,
+    // enabling the NGenKeyword causes info to be generated for every NGen'd method; we should
+    // instead use the native PDB info from ModuleLoad events to get this information.
+    //
+    // Also enabling the rundown keyword causes a bunch of DCStart/DCEnd events to be generated,
+    // which is only useful if we're tracing an already running process.
+    // if STACK is enabled, then every CoreCLR event will also generate a stack event right afterwards
+    use constants::*;
+    let mut info_keywords = CORECLR_LOADER_KEYWORD;
+    if props.coreclr.event_stacks {
+        info_keywords |= CORECLR_STACK_KEYWORD;
+    }
+    if props.coreclr.gc_markers || props.coreclr.gc_suspensions || props.coreclr.gc_detailed_allocs
+    {
+        info_keywords |= CORECLR_GC_KEYWORD;
+    }
+
+    let mut verbose_keywords = CORECLR_JIT_KEYWORD | CORECLR_NGEN_KEYWORD;
+
+    // if we're attaching, ask for a rundown of method info at the start of collection
+    let mut rundown_verbose_keywords = if props.is_attach {
+        CORECLR_LOADER_KEYWORD | CORECLR_JIT_KEYWORD | CORECLR_RUNDOWN_START_KEYWORD
+    } else {
+        0
+    };
+    if props.is_attach && props.coreclr.end_rundown {
+        rundown_verbose_keywords |= CORECLR_RUNDOWN_END_KEYWORD;
+    }
+
+    if props.coreclr.r2r_markers {
+        // R2RGetEntryPoint is a Verbose event.
+        verbose_keywords |= CORECLR_COMPILATION_DIAGNOSTIC_KEYWORD;
+    }
+
+    if props.coreclr.gc_handles {
+        info_keywords |= CORECLR_GC_HANDLE_KEYWORD;
+    }
+
+    if props.coreclr.resolve_type_names {
+        // Only the Type keyword: TypeDiagnostic adds heap walk and type load events,
+        // which we don't need for BulkType and which greatly increase the trace size.
+        info_keywords |= CORECLR_TYPE_KEYWORD;
+    }
 
     if props.coreclr.gc_detailed_allocs {
-        info_keywords |= CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_HIGH_KEYWORD
-            | CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_LOW_KEYWORD;
+        info_keywords |= match props.coreclr.gc_alloc_sampling {
+            CoreClrAllocSampling::Low => CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_LOW_KEYWORD,
+            CoreClrAllocSampling::High => CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_HIGH_KEYWORD,
+            CoreClrAllocSampling::Both => {
+                CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_HIGH_KEYWORD
+                    | CORECLR_GC_SAMPLED_OBJECT_ALLOCATION_LOW_KEYWORD
+            }
+        };
     }
 
     if info_keywords != 0 {
-        providers.push(format!(
-            "Microsoft-Windows-DotNETRuntime:0x{:x}:4",
-            info_keywords
+        providers.push((
+            "Microsoft-Windows-DotNETRuntime".to_string(),
+            info_keywords,
+            4,
         ));
     }
 
@@ -375,16 +1637,18 @@ pub fn coreclr_xperf_args(props: &ElevatedRecordingProps) -> Vec<String> {
         // include the method string names (we would have to pull it out based on MethodID,
         // and I'm not sure which events include the mapping -- MethodJittingStarted is also
         // verbose).
-        providers.push(format!(
-            "Microsoft-Windows-DotNETRuntime:0x{:x}:5",
-            verbose_keywords
+        providers.push((
+            "Microsoft-Windows-DotNETRuntime".to_string(),
+            verbose_keywords,
+            5,
         ));
     }
 
     if rundown_verbose_keywords != 0 {
-        providers.push(format!(
-            "Microsoft-Windows-DotNETRuntimeRundown:0x{:x}:5",
-            rundown_verbose_keywords
+        providers.push((
+            "Microsoft-Windows-DotNETRuntimeRundown".to_string(),
+            rundown_verbose_keywords,
+            5,
         ));
     }
 
@@ -393,6 +1657,21 @@ pub fn coreclr_xperf_args(props: &ElevatedRecordingProps) -> Vec<String> {
     providers
 }
 
+/// Adds the user-specified extra providers to `providers`. If a provider is already
+/// in the list, its keywords are merged into the first entry for that provider, and
+/// the higher of the two levels is used.
+fn merge_extra_providers(providers: &mut Vec<(String, u64, u8)>, extra: &[(String, u64, u8)]) {
+    for (provider, keywords, level) in extra {
+        match providers.iter_mut().find(|(p, _, _)| p == provider) {
+            Some((_, existing_keywords, existing_level)) => {
+                *existing_keywords |= keywords;
+                *existing_level = (*existing_level).max(*level);
+            }
+            None => providers.push((provider.clone(), *keywords, *level)),
+        }
+    }
+}
+
 pub fn handle_coreclr_event(
     context: &mut ProfileContext,
     coreclr_context: &mut CoreClrContext,
@@ -400,11 +1679,13 @@ pub fn handle_coreclr_event(
     parser: &mut Parser,
     is_in_time_range: bool,
 ) {
-    let (gc_markers, gc_suspensions, gc_allocs, event_stacks) = (
+    let (gc_markers, gc_suspensions, gc_allocs, gc_handles, event_stacks, r2r_markers) = (
         coreclr_context.props.gc_markers,
         coreclr_context.props.gc_suspensions,
         coreclr_context.props.gc_detailed_allocs,
+        coreclr_context.props.gc_handles,
         coreclr_context.props.event_stacks,
+        coreclr_context.props.r2r_markers,
     );
 
     let pid = s.process_id();
@@ -416,16 +1697,21 @@ pub fn handle_coreclr_event(
 
     let timestamp_raw = s.timestamp() as u64;
 
+    // Markers are only emitted for events in the time range and on threads that pass the
+    // thread filter. Method loads don't check this, so JIT symbols are unaffected.
+    let emit_markers = is_in_time_range && coreclr_context.thread_filter.includes(tid);
+
     let mut name_parts = s.name().splitn(3, '/');
     let provider = name_parts.next().unwrap();
     let task = name_parts.next().unwrap();
     let opcode = name_parts.next().unwrap();
 
-    match provider {
-        "Microsoft-Windows-DotNETRuntime" | "Microsoft-Windows-DotNETRuntimeRundown" => {}
-        _ => {
-            panic!("Unexpected event {}", s.name())
-        }
+    let suppress_markers =
+        coreclr_context.props.suppress_rundown_markers && is_rundown_event(provider, opcode);
+
+    if !is_coreclr_event(s.name()) {
+        log::warn!("Unexpected CoreCLR event {}", s.name());
+        return;
     }
 
     // TODO -- we may need to use the rundown provider if we trace running processes
@@ -435,6 +1721,20 @@ pub fn handle_coreclr_event(
     // this means we can ignore the ModuleLoadEvents because we'll get dbginfo already mapped properly when the image
     // is loaded.
 
+    if let Ok(clr_instance_id) = parser.try_parse::<u16>("ClrInstanceID") {
+        if coreclr_context.clr_instances.observe(pid, clr_instance_id) {
+            eprintln!(
+                "Process {pid} contains more than one CoreCLR runtime instance; GC markers from different instances may be mixed up."
+            );
+        }
+    }
+
+    // Runtime service threads usually don't have a name, so give them one based on
+    // what they're doing.
+    if let Some(kind) = ManagedThreadKind::from_event(task, opcode) {
+        context.handle_thread_set_default_name(timestamp_raw, pid, tid, kind.thread_name());
+    }
+
     let mut handled = false;
 
     //eprintln!("event: {} [pid: {} tid: {}] {}", timestamp_raw, s.pid(), s.tid(), dotnet_event);
@@ -449,60 +1749,143 @@ pub fn handle_coreclr_event(
     match (task, opcode) {
         ("CLRMethod" | "CLRMethodRundown", method_event) => {
             match method_event {
-            // there's MethodDCStart & MethodDCStartVerbose & MethodLoad
-            // difference between *Verbose and not, is Verbose includes the names
-
-            "MethodLoadVerbose" | "MethodDCStartVerbose"
-            // | "R2RGetEntryPoint" // not sure we need this? R2R methods should be covered by PDB files
-            => {
-                // R2RGetEntryPoint shares a lot of fields with MethodLoadVerbose
-                let is_r2r = method_event == "R2RGetEntryPoint";
-
-                //let method_id: u64 = parser.parse("MethodID");
-                //let clr_instance_id: u32 = parser.parse("ClrInstanceID"); // v1/v2 only
-
-                let method_basename: String = parser.parse("MethodName");
-                let method_namespace: String = parser.parse("MethodNamespace");
-                let method_signature: String = parser.parse("MethodSignature");
-
-                let method_start_address: u64 = if is_r2r { parser.parse("EntryPoint") } else { parser.parse("MethodStartAddress") };
-                let method_size: u32 = parser.parse("MethodSize"); // TODO: R2R doesn't have a size?
+                // there's MethodDCStart & MethodDCStartVerbose & MethodLoad
+                // difference between *Verbose and not, is Verbose includes the names
+                "MethodLoadVerbose" | "MethodDCStartVerbose" | "MethodDCEndVerbose" => {
+                    let kind = MethodLoadKind::from_opcode(method_event).unwrap();
+                    //let method_id: u64 = parser.parse("MethodID");
+                    //let clr_instance_id: u32 = parser.parse("ClrInstanceID"); // v1/v2 only
+
+                    let method_basename: String = parser.parse("MethodName");
+                    let method_namespace: String = parser.parse("MethodNamespace");
+                    let method_signature: String = parser.parse("MethodSignature");
+
+                    let method_start_address: u64 = parser.parse("MethodStartAddress");
+                    let method_size: u32 = parser.parse("MethodSize");
+
+                    // End rundown lists every method that's still loaded, so only the methods
+                    // we haven't seen a Load or DCStart for are new.
+                    if !coreclr_context
+                        .known_methods
+                        .should_add(pid, method_start_address, kind)
+                    {
+                        return;
+                    }
 
-                // There's a v0, v1, and v2 version of this event. There are rules in `eventtrace.cpp` in the runtime
-                // that describe the rules, but basically:
-                // - during a first-JIT, only a v1 (not v0 and not v2+) MethodLoad is emitted.
-                // - during a re-jit, a v2 event is emitted.
-                // - v2 contains a "NativeCodeId" field which will be nonzero in v2. 
-                // - the unique key for a method extent is MethodId + MethodCodeId + extent (hot/cold)
+                    // There's a v0, v1, and v2 version of this event. There are rules in `eventtrace.cpp` in the runtime
+                    // that describe the rules, but basically:
+                    // - during a first-JIT, only a v1 (not v0 and not v2+) MethodLoad is emitted.
+                    // - during a re-jit, a v2 event is emitted.
+                    // - v2 contains a "NativeCodeId" field which will be nonzero in v2.
+                    // - the unique key for a method extent is MethodId + MethodCodeId + extent (hot/cold)
+
+                    // there's some stuff in MethodFlags -- might be tiered JIT info?
+                    // also ClrInstanceID -- we probably won't have more than one runtime, but maybe.
+
+                    let method_name = if method_basename.is_empty()
+                        && method_namespace.is_empty()
+                        && method_signature.is_empty()
+                    {
+                        // Seen with some dynamic methods.
+                        let module_id: u64 = parser.try_parse("ModuleID").unwrap_or(0);
+                        let method_token: u32 = parser.try_parse("MethodToken").unwrap_or(0);
+                        coreclr_fallback_method_name(module_id, method_token, method_start_address)
+                    } else {
+                        coreclr_method_name(&method_basename, &method_namespace, &method_signature)
+                    };
+
+                    if kind == MethodLoadKind::DCEnd {
+                        // This method existed before the capture started, so its symbol is
+                        // added once the rundown is complete, valid from the capture start.
+                        coreclr_context.pending_rundown_methods.add(
+                            pid,
+                            timestamp_raw,
+                            RundownMethod {
+                                name: method_name,
+                                namespace: method_namespace,
+                                start_address: method_start_address,
+                                size: method_size,
+                            },
+                        );
+                    } else {
+                        context.handle_coreclr_method_load(
+                            timestamp_raw,
+                            pid,
+                            method_name,
+                            &method_namespace,
+                            method_start_address,
+                            method_size,
+                        );
+                    }
+                    handled = true;
+                }
+                "DCEndComplete" => {
+                    let methods = coreclr_context.pending_rundown_methods.complete(pid);
+                    context.handle_coreclr_rundown_complete(timestamp_raw, pid, methods);
+                    handled = true;
+                }
+                "MethodUnloadVerbose" => {
+                    let method_start_address: u64 = parser.parse("MethodStartAddress");
+                    coreclr_context
+                        .known_methods
+                        .remove(pid, method_start_address);
+                    context.handle_coreclr_method_unload(timestamp_raw, pid, method_start_address);
+                    handled = true;
+                }
+                "R2RGetEntryPoint" => {
+                    // R2R methods are covered by the PDB files, so there's no need to add a symbol
+                    // for them. But it's still interesting to see when a precompiled method starts
+                    // being used, so emit a marker the first time we see each method.
+                    if !r2r_markers
+                        || !emit_markers
+                        || !context.has_thread_at_time(tid, timestamp_raw)
+                    {
+                        return;
+                    }
 
-                // there's some stuff in MethodFlags -- might be tiered JIT info?
-                // also ClrInstanceID -- we probably won't have more than one runtime, but maybe.
+                    let method_id: u64 = parser.parse("MethodID");
+                    if !coreclr_context
+                        .r2r_entry_points_seen
+                        .insert((pid, method_id))
+                    {
+                        return;
+                    }
 
-                let method_name = format!("{method_basename} [{method_namespace}] \u{2329}{method_signature}\u{232a}");
+                    let method_basename: String = parser.parse("MethodName");
+                    let method_namespace: String = parser.parse("MethodNamespace");
+                    let method_signature: String = parser.parse("MethodSignature");
+                    let method_name =
+                        coreclr_method_name(&method_basename, &method_namespace, &method_signature);
 
-                context.handle_coreclr_method_load(timestamp_raw, pid, method_name, method_start_address, method_size);
-                handled = true;
-            }
-            "ModuleLoad" | "ModuleDCStart" |
-            "ModuleUnload" | "ModuleDCEnd" => {
-                // do we need this for ReadyToRun code?
-
-                //let module_id: u64 = parser.parse("ModuleID");
-                //let assembly_id: u64 = parser.parse("AssemblyId");
-                //let managed_pdb_signature: u?? = parser.parse("ManagedPdbSignature");
-                //let managed_pdb_age: u?? = parser.parse("ManagedPdbAge");
-                //let managed_pdb_path: String = parser.parse("ManagedPdbPath");
-                //let native_pdb_signature: u?? = parser.parse("NativePdbSignature");
-                //let native_pdb_age: u?? = parser.parse("NativePdbAge");
-                //let native_pdb_path: String = parser.parse("NativePdbPath");
-                handled = true;
-            }
-            _ => {
-                // don't care about any other CLRMethod events
-                handled = true;
+                    let category = context.known_category(KnownCategory::CoreClrR2r);
+                    let method_name = context.intern_profile_string(&method_name);
+                    let mh = context.add_thread_instant_marker(
+                        timestamp_raw,
+                        tid,
+                        CoreClrR2rEntryPointMarker(method_name, category),
+                    );
+                    coreclr_context.set_last_event_for_thread(tid, mh);
+                    handled = true;
+                }
+                "ModuleLoad" | "ModuleDCStart" | "ModuleUnload" | "ModuleDCEnd" => {
+                    // do we need this for ReadyToRun code?
+
+                    //let module_id: u64 = parser.parse("ModuleID");
+                    //let assembly_id: u64 = parser.parse("AssemblyId");
+                    //let managed_pdb_signature: u?? = parser.parse("ManagedPdbSignature");
+                    //let managed_pdb_age: u?? = parser.parse("ManagedPdbAge");
+                    //let managed_pdb_path: String = parser.parse("ManagedPdbPath");
+                    //let native_pdb_signature: u?? = parser.parse("NativePdbSignature");
+                    //let native_pdb_age: u?? = parser.parse("NativePdbAge");
+                    //let native_pdb_path: String = parser.parse("NativePdbPath");
+                    handled = true;
+                }
+                _ => {
+                    // don't care about any other CLRMethod events; they show up as raw
+                    // event markers if those are enabled
+                }
             }
         }
-        }
         ("Type", "BulkType") => {
             //         <template tid="BulkType">
             // <data name="Count" inType="win:UInt32"    />
@@ -523,16 +1906,24 @@ pub fn handle_coreclr_event(
             // <ClrInstanceID> %2 </ClrInstanceID>
             // </Type>
             // </UserData>
-            //let count: u32 = parser.parse("Count");
-
-            // uint32 + uint16 at the front (Count and ClrInstanceID), then struct of values. We don't need a Vec<u8> copy.
-            //let values: Vec<u8> = parser.parse("Values");
-            //let values = &s.user_buffer()[6..];
-
-            //eprintln!("Type/BulkType count: {} user_buffer size: {} values len: {}", count, s.user_buffer().len(), values.len());
+            //
+            // This matches BulkType events from both the runtime provider and the rundown
+            // provider, so that attach captures get type names as well. Rundown can
+            // enumerate a lot of types, so we read the values in place.
+            let count: u32 = parser.parse("Count");
+            let _clr_instance_id: u16 = parser.parse("ClrInstanceID");
+
+            // uint32 + uint16 at the front (Count and ClrInstanceID), then struct of values. After
+            // parsing the first two fields, the parser's remaining buffer is the values array, so
+            // we don't need a Vec<u8> copy.
+            let values = parser.buffer;
+            coreclr_context
+                .type_names
+                .add_bulk_type_values(pid, count, values);
+            handled = true;
         }
         ("CLRStack", "CLRStackWalk") => {
-            if !is_in_time_range {
+            if !emit_markers {
                 return;
             }
             // If the STACK keyword is enabled, we get a CLRStackWalk following each CLR event that supports stacks. Not every event
@@ -551,17 +1942,15 @@ pub fn handle_coreclr_event(
             // "Stack" is explicitly declared as length 2 in the manifest, so the first two addresses are in here, rest
             // are in user data buffer.
             let first_addresses: Vec<u8> = parser.parse("Stack");
-            let address_iter = first_addresses
-                .chunks_exact(8)
-                .chain(parser.buffer.chunks_exact(8))
-                .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()));
+            let address_iter = stack_addresses_from_bytes(&first_addresses)
+                .chain(stack_addresses_from_bytes(parser.buffer));
 
             context.handle_coreclr_stack(timestamp_raw, tid, address_iter, marker);
             handled = true;
         }
         ("GarbageCollection", gc_event) => {
             // if we're not in the range, or if the thread isn't recorded (because --main-thread-only)
-            if !is_in_time_range || !context.has_thread_at_time(tid, timestamp_raw) {
+            if !emit_markers || !context.has_thread_at_time(tid, timestamp_raw) {
                 return;
             }
 
@@ -573,17 +1962,25 @@ pub fn handle_coreclr_event(
 
                     // If High/Low flags are set, then we get one of these for every alloc. Otherwise only
                     // when a threshold is hit. (100kb) The count and size are aggregates in that case.
-                    let type_id: u64 = parser.parse("TypeID"); // TODO: convert to str, with bulk type data
-                                                               //let address: u64 = parser.parse("Address");
-                    let _object_count: u32 = parser.parse("ObjectCountForTypeSample");
+                    let type_id: u64 = parser.parse("TypeID");
+                    //let address: u64 = parser.parse("Address");
+                    let object_count: u32 = parser.parse("ObjectCountForTypeSample");
                     let total_size: u64 = parser.parse("TotalSizeForTypeSample");
 
-                    let category = context.known_category(KnownCategory::CoreClrGc);
-                    let clr_type = context.intern_profile_string(&format!("0x{:x}", type_id));
+                    let category = context
+                        .known_category(coreclr_context.gc_category(GcMarkerKind::Allocation));
+                    let clr_type = coreclr_context.interned_type_names.get_or_intern(
+                        pid,
+                        type_id,
+                        coreclr_context.type_names.get(pid, type_id),
+                        |s| context.intern_profile_string(s),
+                    );
+                    let heap = GcHeap::infer(object_count, total_size);
+                    let heap = context.intern_profile_string(&heap.to_string());
                     let mh = context.add_thread_instant_marker(
                         timestamp_raw,
                         tid,
-                        CoreClrGcAllocMarker(clr_type, total_size as f64, category),
+                        CoreClrGcAllocMarker(clr_type, total_size as f64, heap, category),
                     );
                     coreclr_context.set_last_event_for_thread(tid, mh);
                     handled = true;
@@ -594,17 +1991,13 @@ pub fn handle_coreclr_event(
                     }
 
                     let reason: u32 = parser.parse("Reason");
-                    let reason = GcReason::from_u32(reason).or_else(|| {
-                        eprintln!("Unknown CLR GC Triggered reason: {}", reason);
-                        None
-                    });
+                    let reason = GcReason::from_raw(reason);
 
-                    let category = context.known_category(KnownCategory::CoreClrGc);
+                    let category =
+                        context.known_category(coreclr_context.gc_category(GcMarkerKind::Other));
                     let name = context.intern_profile_string("GC Trigger");
-                    let description = context.intern_profile_string(&format!(
-                        "GC Trigger: {}",
-                        DisplayUnknownIfNone(&reason)
-                    ));
+                    let description =
+                        context.intern_profile_string(&format!("GC Trigger: {reason}"));
                     let mh = context.add_thread_instant_marker(
                         timestamp_raw,
                         tid,
@@ -622,17 +2015,15 @@ pub fn handle_coreclr_event(
                     let _count: u32 = parser.parse("Count");
                     let reason: u32 = parser.parse("Reason");
 
-                    let reason = GcSuspendEeReason::from_u32(reason).or_else(|| {
-                        eprintln!("Unknown CLR GCSuspendEEBegin reason: {}", reason);
-                        None
-                    });
+                    let reason = GcSuspendEeReason::from_raw(reason);
 
+                    // The saved description is the reason, which becomes a marker field.
                     coreclr_context.save_gc_marker(
                         tid,
                         timestamp_raw,
                         "GCSuspendEE",
                         "GC Suspended Thread".to_owned(),
-                        format!("Suspended: {}", DisplayUnknownIfNone(&reason)),
+                        reason.to_string(),
                     );
                     handled = true;
                 }
@@ -646,14 +2037,19 @@ pub fn handle_coreclr_event(
                     }
 
                     if let Some(info) = coreclr_context.remove_gc_marker(tid, "GCSuspendEE") {
-                        let category = context.known_category(KnownCategory::CoreClrGc);
+                        let category = context
+                            .known_category(coreclr_context.gc_category(GcMarkerKind::Pause));
                         let name = context.intern_profile_string(&info.name);
-                        let description = context.intern_profile_string(&info.description);
+                        let reason = context.intern_profile_string(&info.description);
                         context.add_thread_interval_marker(
                             info.start_timestamp_raw,
                             timestamp_raw,
                             tid,
-                            CoreClrGcEventMarker(name, description, category),
+                            CoreClrGcSuspensionMarker {
+                                name,
+                                reason,
+                                category,
+                            },
                         );
                     }
                     handled = true;
@@ -668,29 +2064,19 @@ pub fn handle_coreclr_event(
                     let reason: u32 = parser.parse("Reason");
                     let gc_type: u32 = parser.parse("Type");
 
-                    let reason = GcReason::from_u32(reason).or_else(|| {
-                        eprintln!("Unknown CLR GCStart reason: {}", reason);
-                        None
-                    });
+                    let reason = GcReason::from_raw(reason);
 
-                    let gc_type = GcType::from_u32(gc_type).or_else(|| {
-                        eprintln!("Unknown CLR GCStart type: {}", gc_type);
-                        None
-                    });
+                    let gc_type = GcType::from_raw(gc_type);
 
-                    // TODO: use gc_type_str as the name
-                    coreclr_context.save_gc_marker(
+                    coreclr_context.gc_start_on_thread.insert(
                         tid,
-                        timestamp_raw,
-                        "GC",
-                        "GC".to_owned(),
-                        format!(
-                            "{}: {} (GC #{}, gen{})",
-                            DisplayUnknownIfNone(&gc_type),
-                            DisplayUnknownIfNone(&reason),
+                        SavedGcStartInfo {
+                            start_timestamp_raw: timestamp_raw,
                             count,
-                            depth
-                        ),
+                            depth,
+                            reason,
+                            gc_type,
+                        },
                     );
                     handled = true;
                 }
@@ -701,85 +2087,338 @@ pub fn handle_coreclr_event(
 
                     //let count: u32 = parser.parse("Count");
                     //let depth: u32 = parser.parse("Depth");
-                    if let Some(info) = coreclr_context.remove_gc_marker(tid, "GC") {
-                        let category = context.known_category(KnownCategory::CoreClrGc);
-                        let name = context.intern_profile_string(&info.name);
-                        let description = context.intern_profile_string(&info.description);
+                    if let Some(info) = coreclr_context.gc_start_on_thread.remove(&tid) {
+                        let marker_kind = info.reason.pause_marker_kind();
+                        let category =
+                            context.known_category(coreclr_context.gc_category(marker_kind));
+                        let reason = context.intern_profile_string(&info.reason.to_string());
+                        let gc_type = context.intern_profile_string(&info.gc_type.to_string());
+                        let pause_kind = context.intern_profile_string(info.gc_type.pause_kind());
+                        let pause_ms = context.duration_ms(info.start_timestamp_raw, timestamp_raw);
                         context.add_thread_interval_marker(
                             info.start_timestamp_raw,
                             timestamp_raw,
                             tid,
-                            CoreClrGcEventMarker(name, description, category),
+                            CoreClrGcDetailedMarker {
+                                count: info.count,
+                                generation: info.depth,
+                                reason,
+                                gc_type,
+                                pause_kind,
+                                pause_ms,
+                                category,
+                            },
                         );
                     }
                     handled = true;
                 }
                 "SetGCHandle" => {
-                    // TODO
+                    if !gc_handles {
+                        return;
+                    }
+
+                    // HandleID, ObjectID, Kind, Generation, AppDomainID, ClrInstanceID
+                    let handle_id: u64 = parser.parse("HandleID");
+                    let object_id: u64 = parser.parse("ObjectID");
+                    let kind: u32 = parser.parse("Kind");
+                    let generation: u32 = parser.parse("Generation");
+
+                    let kind = GcHandleKind::from_raw(kind);
+
+                    if coreclr_context.gc_handles.set(pid, handle_id) {
+                        context.handle_coreclr_gc_handle_count_change(timestamp_raw, pid, 1.0);
+                    }
+
+                    let category =
+                        context.known_category(coreclr_context.gc_category(GcMarkerKind::Other));
+                    let name = context.intern_profile_string("GC Handle Created");
+                    let description = context.intern_profile_string(&format!(
+                        "{} handle 0x{:x} for object 0x{:x} (gen {}), {} live",
+                        kind,
+                        handle_id,
+                        object_id,
+                        generation,
+                        coreclr_context.gc_handles.live_count(pid)
+                    ));
+                    let mh = context.add_thread_instant_marker(
+                        timestamp_raw,
+                        tid,
+                        CoreClrGcEventMarker(name, description, category),
+                    );
+                    coreclr_context.set_last_event_for_thread(tid, mh);
+                    handled = true;
                 }
                 "DestroyGCHandle" => {
-                    // TODO
+                    if !gc_handles {
+                        return;
+                    }
+
+                    // HandleID, ClrInstanceID
+                    let handle_id: u64 = parser.parse("HandleID");
+
+                    if coreclr_context.gc_handles.destroy(pid, handle_id) {
+                        context.handle_coreclr_gc_handle_count_change(timestamp_raw, pid, -1.0);
+                    }
+
+                    let category =
+                        context.known_category(coreclr_context.gc_category(GcMarkerKind::Other));
+                    let name = context.intern_profile_string("GC Handle Destroyed");
+                    let description = context.intern_profile_string(&format!(
+                        "Handle 0x{:x}, {} live",
+                        handle_id,
+                        coreclr_context.gc_handles.live_count(pid)
+                    ));
+                    let mh = context.add_thread_instant_marker(
+                        timestamp_raw,
+                        tid,
+                        CoreClrGcEventMarker(name, description, category),
+                    );
+                    coreclr_context.set_last_event_for_thread(tid, mh);
+                    handled = true;
                 }
-                "GCFinalizersBegin" | "GCFinalizersEnd" | "FinalizeObject" => {
-                    // TODO: create an interval
+                "GCGlobalHeapHistory" => {
+                    if !gc_markers {
+                        return;
+                    }
+
+                    let history = GcGlobalHeapHistoryEvent::parse(parser);
+
+                    let category =
+                        context.known_category(coreclr_context.gc_category(GcMarkerKind::Other));
+                    let name = context.intern_profile_string("GC Global Heap History");
+                    let description =
+                        context.intern_profile_string(&format!("Global heap history: {history}"));
+                    let mh = context.add_thread_instant_marker(
+                        timestamp_raw,
+                        tid,
+                        CoreClrGcEventMarker(name, description, category),
+                    );
+                    coreclr_context.set_last_event_for_thread(tid, mh);
                     handled = true;
                 }
-                "GCCreateSegment" | "GCFreeSegment" | "GCDynamicEvent" | "GCHeapStats" => {
-                    // don't care
+                "GCFinalizersBegin" => {
+                    if !gc_markers {
+                        return;
+                    }
+
+                    coreclr_context.save_gc_marker(
+                        tid,
+                        timestamp_raw,
+                        "GCFinalizers",
+                        "GC Finalizers".to_owned(),
+                        "Running finalizers".to_owned(),
+                    );
                     handled = true;
                 }
-                _ => {
-                    // don't care
+                "GCFinalizersEnd" => {
+                    if !gc_markers {
+                        return;
+                    }
+
+                    if let Some(info) = coreclr_context.remove_gc_marker(tid, "GCFinalizers") {
+                        let category = context
+                            .known_category(coreclr_context.gc_category(GcMarkerKind::Finalizer));
+                        let name = context.intern_profile_string(&info.name);
+                        let description = match parser.try_parse::<u32>("Count") {
+                            Ok(count) => format!("{} ({count} finalized)", info.description),
+                            Err(_) => info.description,
+                        };
+                        let description = context.intern_profile_string(&description);
+                        context.add_thread_interval_marker(
+                            info.start_timestamp_raw,
+                            timestamp_raw,
+                            tid,
+                            CoreClrGcEventMarker(name, description, category),
+                        );
+                    }
+                    handled = true;
+                }
+                "FinalizeObject" => {
+                    // don't care about individual objects
+                    handled = true;
+                }
+                "GCDynamicEvent" => {
+                    if !gc_markers {
+                        return;
+                    }
+
+                    // The runtime uses this to log new GC events without manifest changes.
+                    // We don't know the layout of the payload, so we show it as hex.
+                    let event = GcDynamicEvent {
+                        name: parser.parse("Name"),
+                        payload: parser.try_parse("Payload").unwrap_or_default(),
+                    };
+                    let category =
+                        context.known_category(coreclr_context.gc_category(GcMarkerKind::Other));
+                    let name = context.intern_profile_string(&event.name);
+                    let description = context.intern_profile_string(&event.description());
+                    let mh = context.add_thread_instant_marker(
+                        timestamp_raw,
+                        tid,
+                        CoreClrGcEventMarker(name, description, category),
+                    );
+                    coreclr_context.set_last_event_for_thread(tid, mh);
                     handled = true;
                 }
+                _ => {
+                    // don't care (e.g. GCCreateSegment, GCFreeSegment, GCHeapStats);
+                    // these show up as raw event markers if those are enabled
+                }
             }
         }
-        ("CLRRuntimeInformation", _) => {
+        ("CLRRuntimeInformation" | "CLRRuntimeInformationRundown", _) => {
+            // Logged at startup, and again for each process during rundown; one marker
+            // per process is enough.
+            if !coreclr_context.runtime_info_seen.insert(pid)
+                || suppress_markers
+                || !emit_markers
+                || !context.has_thread_at_time(tid, timestamp_raw)
+            {
+                return;
+            }
+
+            let info = RuntimeInformationEvent::parse(parser);
+            let category = context.known_category(KnownCategory::CoreClrLoader);
+            let version = context.intern_profile_string(&info.version_label());
+            let command_line = context.intern_profile_string(&info.command_line);
+            let runtime_dll_path = context.intern_profile_string(&info.runtime_dll_path);
+            let mh = context.add_thread_instant_marker(
+                timestamp_raw,
+                tid,
+                CoreClrRuntimeInformationMarker {
+                    version,
+                    command_line,
+                    runtime_dll_path,
+                    category,
+                },
+            );
+            coreclr_context.set_last_event_for_thread(tid, mh);
             handled = true;
         }
-        ("CLRLoader", _) => {
-            // AppDomain, Assembly, Module Load/Unload
-            handled = true;
+        ("CLRLoader" | "CLRLoaderRundown", loader_event) => {
+            // AppDomain, Assembly, Module Load/Unload; we only create markers for assemblies.
+            let assembly_event = match loader_event {
+                "AssemblyLoad" | "AssemblyDCStart" => Some((true, "Assembly Load")),
+                "AssemblyUnload" => Some((false, "Assembly Unload")),
+                _ => None,
+            };
+            if let Some((is_load, marker_name)) = assembly_event {
+                if !emit_markers || !context.has_thread_at_time(tid, timestamp_raw) {
+                    return;
+                }
+
+                let assembly_id: u64 = parser.parse("AssemblyID");
+                let assembly_name: String = parser.parse("FullyQualifiedAssemblyName");
+
+                // With rundown, assemblies which were loaded during the recording are
+                // enumerated again, so only emit one load marker per assembly.
+                let key = (pid, assembly_id);
+                if is_load && !coreclr_context.loaded_assemblies.insert(key) {
+                    return;
+                }
+                if !is_load {
+                    coreclr_context.loaded_assemblies.remove(&key);
+                }
+                if suppress_markers {
+                    return;
+                }
+
+                let category = context.known_category(KnownCategory::CoreClrLoader);
+                let name = context.intern_profile_string(marker_name);
+                let short_name = context.intern_profile_string(assembly_short_name(&assembly_name));
+                let full_name = context.intern_profile_string(&assembly_name);
+                let mh = context.add_thread_instant_marker(
+                    timestamp_raw,
+                    tid,
+                    CoreClrAssemblyMarker {
+                        name,
+                        short_name,
+                        full_name,
+                        category,
+                    },
+                );
+                coreclr_context.set_last_event_for_thread(tid, mh);
+                handled = true;
+            }
         }
         _ => {}
     }
 
-    if !handled && coreclr_context.unknown_event_markers {
+    // Any event that we decoded but don't have a dedicated marker for can be shown as a
+    // "raw" marker with the event name and its properties. This is off by default (it's
+    // enabled with --unknown-event-markers), because it produces a lot of markers, but
+    // it's useful to find out what's in a trace.
+    if wants_raw_event_marker(
+        coreclr_context.unknown_event_markers,
+        handled,
+        suppress_markers,
+    ) && emit_markers
+        && context.has_thread_at_time(tid, timestamp_raw)
+    {
         let text = event_properties_to_string(s, parser, None);
-        let name = context.intern_profile_string(s.name().split_once('/').unwrap().1);
-        let description = context.intern_profile_string(&text);
+        let event_name = context.intern_profile_string(s.name().split_once('/').unwrap().1);
+        let properties = context.intern_profile_string(&text);
+        let keywords =
+            context.intern_profile_string(&coreclr_keywords_description(provider, s.keyword()));
         let marker_handle = context.add_thread_instant_marker(
             timestamp_raw,
             tid,
-            OtherClrMarker(name, description),
+            CoreClrRawEventMarker(event_name, properties, keywords),
         );
 
         coreclr_context.set_last_event_for_thread(tid, marker_handle);
     }
 }
 
+/// Returns whether an event gets a `CoreClrRawEventMarker`: only if raw event markers
+/// are enabled and no other marker was created for the event.
+fn wants_raw_event_marker(
+    unknown_event_markers: bool,
+    handled: bool,
+    suppress_markers: bool,
+) -> bool {
+    unknown_event_markers && !handled && !suppress_markers
+}
+
+/// A marker for any CoreCLR event that doesn't have a more specific marker.
+/// Fields are the event name (task/opcode), the stringified event properties and
+/// the event's keywords.
 #[derive(Debug, Clone)]
-pub struct OtherClrMarker(StringHandle, StringHandle);
+pub struct CoreClrRawEventMarker(StringHandle, StringHandle, StringHandle);
 
-impl StaticSchemaMarker for OtherClrMarker {
-    const UNIQUE_MARKER_TYPE_NAME: &'static str = "OtherClrMarker";
+impl StaticSchemaMarker for CoreClrRawEventMarker {
+    const UNIQUE_MARKER_TYPE_NAME: &'static str = "CoreClrRawEvent";
 
     fn schema() -> MarkerSchema {
         MarkerSchema {
             type_name: Self::UNIQUE_MARKER_TYPE_NAME.into(),
             locations: vec![MarkerLocation::MarkerChart, MarkerLocation::MarkerTable],
-            chart_label: Some("{marker.data.name}".into()),
-            tooltip_label: Some("{marker.data.name}".into()),
-            table_label: Some("{marker.data.name}".into()),
-            fields: vec![MarkerFieldSchema {
-                key: "name".into(),
-                label: "Name".into(),
-                format: MarkerFieldFormat::String,
-                searchable: true,
-            }],
+            chart_label: Some("{marker.data.event}".into()),
+            tooltip_label: Some("{marker.data.event}".into()),
+            table_label: Some("{marker.data.event}: {marker.data.properties}".into()),
+            fields: vec![
+                MarkerFieldSchema {
+                    key: "event".into(),
+                    label: "Event".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+                MarkerFieldSchema {
+                    key: "properties".into(),
+                    label: "Properties".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+                MarkerFieldSchema {
+                    key: "keywords".into(),
+                    label: "Keywords".into(),
+                    format: MarkerFieldFormat::String,
+                    searchable: true,
+                },
+            ],
             static_fields: vec![MarkerStaticField {
                 label: "Description".into(),
-                value: "CoreCLR marker of unknown type.".into(),
+                value: "CoreCLR event without a dedicated marker type.".into(),
             }],
         }
     }
@@ -792,11 +2431,711 @@ impl StaticSchemaMarker for OtherClrMarker {
         CategoryHandle::OTHER
     }
 
-    fn string_field_value(&self, _field_index: u32) -> StringHandle {
-        self.1
+    fn string_field_value(&self, field_index: u32) -> StringHandle {
+        match field_index {
+            0 => self.0,
+            1 => self.1,
+            2 => self.2,
+            _ => unreachable!(),
+        }
     }
 
     fn number_field_value(&self, _field_index: u32) -> f64 {
         unreachable!()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::shared::timestamp_converter::TimestampConverter;
+
+    fn recording_props(coreclr: CoreClrProfileProps) -> ElevatedRecordingProps {
+        ElevatedRecordingProps {
+            time_limit_seconds: None,
+            interval_nanos: 1_000_000,
+            coreclr,
+            vm_hack: false,
+            is_attach: false,
+            gfx: false,
+            browsers: false,
+        }
+    }
+
+    fn bulk_type_value(type_id: u64, name: &str, type_parameters: &[u64]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&type_id.to_le_bytes());
+        buf.extend_from_slice(&0x7ff8_0000_1000u64.to_le_bytes()); // ModuleID
+        buf.extend_from_slice(&0u32.to_le_bytes()); // TypeNameID
+        buf.extend_from_slice(&0u32.to_le_bytes()); // Flags
+        buf.push(0x12); // CorElementType
+        for unit in name.encode_utf16().chain([0]) {
+            buf.extend_from_slice(&unit.to_le_bytes());
+        }
+        buf.extend_from_slice(&(type_parameters.len() as u32).to_le_bytes());
+        for type_parameter in type_parameters {
+            buf.extend_from_slice(&type_parameter.to_le_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn test_bulk_type_names() {
+        let mut values = bulk_type_value(0x1000, "System.String", &[]);
+        values.extend(bulk_type_value(
+            0x2000,
+            "System.Collections.Generic.List`1",
+            &[0x1000],
+        ));
+
+        // A rundown BulkType lets a later allocation in the same process find its type name.
+        let mut type_names = ClrTypeNames::default();
+        type_names.add_bulk_type_values(42, 2, &values);
+        assert_eq!(type_names.get(42, 0x1000), Some("System.String"));
+        assert_eq!(
+            type_names.get(42, 0x2000),
+            Some("System.Collections.Generic.List`1")
+        );
+        assert_eq!(type_names.get(43, 0x1000), None);
+
+        // Truncated entries are ignored.
+        let mut type_names = ClrTypeNames::default();
+        type_names.add_bulk_type_values(42, 2, &values[..values.len() - 4]);
+        assert_eq!(type_names.get(42, 0x1000), Some("System.String"));
+        assert_eq!(type_names.get(42, 0x2000), None);
+    }
+
+    #[test]
+    fn test_interned_type_names_cap() {
+        let mut profile = Profile::new(
+            "",
+            ReferenceTimestamp::from_millis_since_unix_epoch(0.0),
+            SamplingInterval::from_millis(1),
+        );
+        let mut interned = InternedTypeNames::new(2);
+        let mut intern = |pid, type_id, name| {
+            let handle = interned.get_or_intern(pid, type_id, name, |s| profile.intern_string(s));
+            profile.get_string(handle).to_string()
+        };
+        assert_eq!(intern(1, 0x10, Some("System.String")), "System.String");
+        assert_eq!(intern(1, 0x20, None), "0x20");
+        // Past the cap, new types share the fallback...
+        assert_eq!(intern(1, 0x30, Some("System.Object")), "(many types)");
+        assert_eq!(intern(2, 0x10, Some("System.String")), "(many types)");
+        // ...but types we've already seen keep their names, and pick up late names.
+        assert_eq!(intern(1, 0x10, Some("System.String")), "System.String");
+        assert_eq!(intern(1, 0x20, Some("System.Byte[]")), "System.Byte[]");
+    }
+
+    #[test]
+    fn test_coreclr_method_name() {
+        assert_eq!(
+            coreclr_method_name("Main", "Program", "void  (class System.String[])"),
+            "Main [Program] \u{2329}void  (class System.String[])\u{232a}"
+        );
+    }
+
+    #[test]
+    fn test_method_rundown() {
+        assert_eq!(
+            MethodLoadKind::from_opcode("MethodDCStartVerbose"),
+            Some(MethodLoadKind::DCStart)
+        );
+        assert_eq!(
+            MethodLoadKind::from_opcode("MethodDCEndVerbose"),
+            Some(MethodLoadKind::DCEnd)
+        );
+        assert_eq!(MethodLoadKind::from_opcode("MethodUnloadVerbose"), None);
+
+        let mut methods = KnownMethods::default();
+        assert!(methods.should_add(100, 0x1000, MethodLoadKind::DCStart));
+        assert!(methods.should_add(100, 0x2000, MethodLoadKind::Load));
+        // The end rundown repeats methods from the start rundown and from loads.
+        assert!(!methods.should_add(100, 0x1000, MethodLoadKind::DCEnd));
+        assert!(!methods.should_add(100, 0x2000, MethodLoadKind::DCEnd));
+        // Methods that are only in the end rundown are added.
+        assert!(methods.should_add(100, 0x3000, MethodLoadKind::DCEnd));
+        assert!(methods.should_add(200, 0x1000, MethodLoadKind::DCEnd));
+        // Loads are always added, in case the address was reused.
+        assert!(methods.should_add(100, 0x1000, MethodLoadKind::Load));
+        // After an unload, a rundown can list a different method at the same address.
+        methods.remove(100, 0x1000);
+        assert!(methods.should_add(100, 0x1000, MethodLoadKind::DCEnd));
+    }
+
+    #[test]
+    fn test_dc_end_complete_finishes_rundown() {
+        let method = |start_address| RundownMethod {
+            name: "M".to_string(),
+            namespace: "N".to_string(),
+            start_address,
+            size: 0x10,
+        };
+        let mut pending = PendingRundownMethods::default();
+        pending.add(100, 10, method(0x1000));
+        pending.add(100, 11, method(0x2000));
+        pending.add(200, 12, method(0x1000));
+
+        let methods = pending.complete(100);
+        let addresses: Vec<u64> = methods.iter().map(|m| m.start_address).collect();
+        assert_eq!(addresses, vec![0x1000, 0x2000]);
+        // The rundown window of this process is closed; other processes are unaffected.
+        assert!(pending.complete(100).is_empty());
+        assert_eq!(pending.complete(200).len(), 1);
+    }
+
+    #[test]
+    fn test_attach_end_rundown() {
+        let attach_props = |end_rundown| ElevatedRecordingProps {
+            is_attach: true,
+            ..recording_props(CoreClrProfileProps {
+                enabled: true,
+                end_rundown,
+                ..Default::default()
+            })
+        };
+        // The end rundown is only requested if asked for, because it repeats the
+        // start rundown.
+        assert_eq!(
+            coreclr_xperf_args(&attach_props(false)).last().unwrap(),
+            "Microsoft-Windows-DotNETRuntimeRundown:0x58:5"
+        );
+        assert_eq!(
+            coreclr_xperf_args(&attach_props(true)).last().unwrap(),
+            "Microsoft-Windows-DotNETRuntimeRundown:0x158:5"
+        );
+    }
+
+    #[test]
+    fn test_unfinished_rundowns_are_flushed() {
+        let method = |start_address| RundownMethod {
+            name: "M".to_string(),
+            namespace: "N".to_string(),
+            start_address,
+            size: 0x10,
+        };
+        let mut pending = PendingRundownMethods::default();
+        pending.add(300, 20, method(0x3000));
+        pending.add(100, 10, method(0x1000));
+        pending.add(100, 15, method(0x2000));
+
+        // Without a DCEndComplete event, the methods are still there at the end.
+        let rundowns = pending.complete_all();
+        let summary: Vec<(u32, u64, usize)> = rundowns
+            .iter()
+            .map(|(pid, timestamp_raw, methods)| (*pid, *timestamp_raw, methods.len()))
+            .collect();
+        assert_eq!(summary, vec![(100, 15, 2), (300, 20, 1)]);
+        assert!(pending.complete_all().is_empty());
+    }
+
+    #[test]
+    fn test_coreclr_fallback_method_name() {
+        assert_eq!(
+            coreclr_fallback_method_name(0x7ffb_1234_0000, 0x0600_0012, 0x7ffb_5678_9000),
+            "DynamicMethod@Module0x7ffb12340000+0x6000012"
+        );
+        assert_eq!(
+            coreclr_fallback_method_name(0, 0, 0x7ffb_5678_9000),
+            "JIT[0x7ffb56789000]"
+        );
+    }
+
+    #[test]
+    fn test_r2r_markers_keyword() {
+        let props = recording_props(CoreClrProfileProps {
+            r2r_markers: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            coreclr_xperf_args(&props),
+            vec![
+                "Microsoft-Windows-DotNETRuntime:0x8:4",
+                "Microsoft-Windows-DotNETRuntime:0x2000000030:5",
+            ]
+        );
+
+        // The keyword has to go with the Verbose level, or we get no R2RGetEntryPoint events.
+        let keyword = constants::CORECLR_COMPILATION_DIAGNOSTIC_KEYWORD;
+        let levels: Vec<u8> = coreclr_providers(&props)
+            .into_iter()
+            .filter(|(_, keywords, _)| keywords & keyword != 0)
+            .map(|(_, _, level)| level)
+            .collect();
+        assert_eq!(levels, vec![5]);
+    }
+
+    #[test]
+    fn test_coreclr_overhead_estimate() {
+        let (overhead, reasons) = coreclr_overhead_estimate(&CoreClrProfileProps {
+            enabled: true,
+            gc_markers: true,
+            ..Default::default()
+        });
+        assert_eq!(overhead, CoreClrOverhead::Low);
+        assert!(reasons.is_empty());
+
+        let (overhead, _) = coreclr_overhead_estimate(&CoreClrProfileProps {
+            gc_detailed_allocs: true,
+            gc_alloc_sampling: CoreClrAllocSampling::Low,
+            ..Default::default()
+        });
+        assert_eq!(overhead, CoreClrOverhead::Moderate);
+
+        let (overhead, reasons) = coreclr_overhead_estimate(&CoreClrProfileProps {
+            gc_detailed_allocs: true,
+            gc_alloc_sampling: CoreClrAllocSampling::High,
+            gc_handles: true,
+            ..Default::default()
+        });
+        assert_eq!(overhead, CoreClrOverhead::VeryHigh);
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(reasons[0].0, CoreClrOverhead::VeryHigh);
+        assert!(reasons[0].1.starts_with("gc-detailed-allocs-high"));
+        assert!(overhead >= CoreClrOverhead::High);
+    }
+
+    #[test]
+    fn test_raw_event_markers() {
+        // An event without a dedicated marker only gets a raw marker if they're enabled.
+        assert!(wants_raw_event_marker(true, false, false));
+        assert!(!wants_raw_event_marker(false, false, false));
+        // Events which got a dedicated marker, or whose markers are suppressed, never do.
+        assert!(!wants_raw_event_marker(true, true, false));
+        assert!(!wants_raw_event_marker(true, false, true));
+
+        let mut profile = Profile::new(
+            "",
+            ReferenceTimestamp::from_millis_since_unix_epoch(0.0),
+            SamplingInterval::from_millis(1),
+        );
+        let marker = CoreClrRawEventMarker(
+            profile.intern_string("GC/GCHeapStats"),
+            profile.intern_string("GenerationSize0: 1024"),
+            profile.intern_string("GC"),
+        );
+        let name = marker.name(&mut profile);
+        assert_eq!(profile.get_string(name), "GC/GCHeapStats");
+        assert_eq!(
+            profile.get_string(marker.string_field_value(1)),
+            "GenerationSize0: 1024"
+        );
+        assert_eq!(profile.get_string(marker.string_field_value(2)), "GC");
+    }
+
+    #[test]
+    fn test_type_names_keyword() {
+        // Asking for type names is enough to enable the provider.
+        let props = recording_props(CoreClrProfileProps {
+            resolve_type_names: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            coreclr_xperf_args(&props),
+            vec![
+                "Microsoft-Windows-DotNETRuntime:0x80008:4",
+                "Microsoft-Windows-DotNETRuntime:0x30:5",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_thread_filter() {
+        let all = CoreClrThreadFilter::default();
+        assert!(all.includes(1) && all.includes(2));
+
+        let exclude = CoreClrThreadFilter {
+            include: vec![],
+            exclude: vec![2],
+        };
+        assert!(exclude.includes(1));
+        assert!(!exclude.includes(2));
+
+        let include = CoreClrThreadFilter {
+            include: vec![1, 3],
+            exclude: vec![3],
+        };
+        assert!(include.includes(1));
+        assert!(!include.includes(2));
+        assert!(!include.includes(3));
+    }
+
+    #[test]
+    fn test_clr_instance_tracker() {
+        let mut tracker = ClrInstanceTracker::default();
+        assert!(!tracker.observe(100, 9));
+        assert!(!tracker.observe(100, 9));
+        assert!(!tracker.observe(200, 7));
+        // A second instance in process 100 is reported once.
+        assert!(tracker.observe(100, 10));
+        assert!(!tracker.observe(100, 10));
+        assert!(!tracker.observe(100, 9));
+        assert!(!tracker.observe(200, 7));
+    }
+
+    #[test]
+    fn test_gc_handle_kind() {
+        assert_eq!(GcHandleKind::from_raw(0).to_string(), "Weak (short)");
+        assert_eq!(GcHandleKind::from_raw(3).to_string(), "Pinned");
+        assert_eq!(GcHandleKind::from_raw(7).to_string(), "Async pinned");
+        assert_eq!(GcHandleKind::from_raw(9).to_string(), "Weak (WinRT)");
+        assert_eq!(GcHandleKind::from_raw(10), GcHandleKind::Unknown(10));
+        assert_eq!(GcHandleKind::from_raw(10).to_string(), "Unknown (10)");
+    }
+
+    #[test]
+    fn test_gc_handle_tracker() {
+        let mut tracker = GcHandleTracker::default();
+        assert!(tracker.set(100, 0x1000));
+        assert!(tracker.set(200, 0x1000));
+        assert_eq!(tracker.live_count(100), 1);
+        assert!(tracker.destroy(100, 0x1000));
+        assert_eq!(tracker.live_count(100), 0);
+        assert_eq!(tracker.live_count(200), 1);
+        // Handles created before the recording started don't make the count go negative.
+        assert!(!tracker.destroy(100, 0x2000));
+        assert_eq!(tracker.live_count(100), 0);
+    }
+
+    #[test]
+    fn test_managed_thread_kind() {
+        let kind = ManagedThreadKind::from_event("GarbageCollection", "GCFinalizersBegin");
+        assert_eq!(kind, Some(ManagedThreadKind::Finalizer));
+        assert_eq!(kind.unwrap().thread_name(), "Finalizer");
+        assert_eq!(
+            ManagedThreadKind::from_event("GarbageCollection", "win:Start"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_gc_heap_inference() {
+        // A single 100KB array is a large object.
+        assert_eq!(GcHeap::infer(1, 100_000), GcHeap::LargeObject);
+        assert_eq!(GcHeap::infer(1, 85_000), GcHeap::LargeObject);
+        // 100KB worth of small strings is not.
+        assert_eq!(GcHeap::infer(1000, 100_000), GcHeap::SmallObject);
+        assert_eq!(GcHeap::infer(1, 84_999), GcHeap::SmallObject);
+        assert_eq!(GcHeap::infer(0, 200_000), GcHeap::LargeObject);
+        assert_eq!(GcHeap::LargeObject.to_string(), "LOH");
+
+        let fields = CoreClrGcAllocMarker::schema().fields;
+        assert_eq!(fields[2].key, "heap");
+        assert!(fields[2].searchable);
+    }
+
+    #[test]
+    fn test_gc_dynamic_event_description() {
+        let event = GcDynamicEvent {
+            name: "SizeAdaptationTuning".to_string(),
+            payload: vec![0x01, 0x00, 0xab, 0xff],
+        };
+        assert_eq!(
+            event.description(),
+            "SizeAdaptationTuning: 4 bytes, 0100abff"
+        );
+
+        let empty = GcDynamicEvent {
+            name: "Empty".to_string(),
+            payload: vec![],
+        };
+        assert_eq!(empty.description(), "Empty: 0 bytes");
+
+        let long = GcDynamicEvent {
+            name: "Long".to_string(),
+            payload: vec![0xcc; 100],
+        };
+        let description = long.description();
+        assert!(description.starts_with("Long: 100 bytes, cccc"));
+        assert!(description.ends_with("cc..."));
+        assert_eq!(description.matches("cc").count(), 64);
+    }
+
+    #[test]
+    fn test_runtime_information_version() {
+        let info = RuntimeInformationEvent {
+            sku: 2,
+            vm_version: [8, 0, 324, 11423],
+            command_line: "dotnet app.dll".to_string(),
+            runtime_dll_path: r"C:\Program Files\dotnet\shared\coreclr.dll".to_string(),
+        };
+        assert_eq!(info.version_label(), ".NET 8.0.324.11423");
+
+        let framework = RuntimeInformationEvent {
+            sku: 1,
+            vm_version: [4, 0, 30319, 42000],
+            ..info
+        };
+        assert_eq!(framework.version_label(), ".NET Framework 4.0.30319.42000");
+    }
+
+    #[test]
+    fn test_assembly_short_name() {
+        assert_eq!(
+            assembly_short_name(
+                "Newtonsoft.Json, Version=13.0.0.0, Culture=neutral, PublicKeyToken=30ad4fe6b2a6aeed"
+            ),
+            "Newtonsoft.Json"
+        );
+        assert_eq!(assembly_short_name("MyApp"), "MyApp");
+    }
+
+    #[test]
+    fn test_gc_categories() {
+        let alloc = GcMarkerKind::Allocation.known_category(true);
+        let pause = GcMarkerKind::Pause.known_category(true);
+        assert_eq!(alloc, KnownCategory::CoreClrGcAlloc);
+        assert_eq!(pause, KnownCategory::CoreClrGcPause);
+        assert_ne!(alloc, pause);
+
+        assert_eq!(
+            GcMarkerKind::Allocation.known_category(false),
+            KnownCategory::CoreClrGc
+        );
+        assert_eq!(
+            GcMarkerKind::Pause.known_category(false),
+            KnownCategory::CoreClrGc
+        );
+    }
+
+    #[test]
+    fn test_gc_reason_unknown() {
+        assert_eq!(GcReason::from_raw(1), GcReason::Induced);
+        assert_eq!(GcReason::from_raw(9), GcReason::InducedLowMemory);
+        assert_eq!(GcReason::from_raw(10), GcReason::Unknown(10));
+        let reason = GcReason::from_raw(42);
+        assert_eq!(reason, GcReason::Unknown(42));
+        assert_eq!(reason.to_string(), "Unknown (42)");
+    }
+
+    #[test]
+    fn test_gc_enums_unknown() {
+        assert_eq!(GcType::from_raw(1), GcType::Background);
+        let gc_type = GcType::from_raw(7);
+        assert_eq!(gc_type, GcType::Unknown(7));
+        assert_eq!(gc_type.is_blocking(), None);
+        assert_eq!(gc_type.to_string(), "Unknown GC type (7)");
+
+        assert_eq!(GcSuspendEeReason::from_raw(5), GcSuspendEeReason::Debugger);
+        let reason = GcSuspendEeReason::from_raw(8);
+        assert_eq!(reason, GcSuspendEeReason::Unknown(8));
+        assert_eq!(reason.to_string(), "Unknown (8)");
+    }
+
+    #[test]
+    fn test_induced_gc_category() {
+        let induced = GcReason::from_raw(1).pause_marker_kind();
+        let alloc = GcReason::from_raw(0).pause_marker_kind();
+        assert_eq!(induced, GcMarkerKind::InducedPause);
+        assert_eq!(alloc, GcMarkerKind::Pause);
+        assert_eq!(
+            GcReason::from_raw(9).pause_marker_kind(),
+            GcMarkerKind::InducedPause
+        );
+        assert_eq!(
+            GcReason::from_raw(42).pause_marker_kind(),
+            GcMarkerKind::Pause
+        );
+
+        for separate_categories in [false, true] {
+            assert_eq!(
+                induced.known_category(separate_categories),
+                KnownCategory::CoreClrGcInduced
+            );
+            assert_ne!(
+                alloc.known_category(separate_categories),
+                KnownCategory::CoreClrGcInduced
+            );
+        }
+    }
+
+    #[test]
+    fn test_gc_global_mechanisms_display() {
+        assert_eq!(GcGlobalMechanisms::empty().to_string(), "None");
+        assert_eq!(
+            GcGlobalMechanisms::from_bits_retain(0x6).to_string(),
+            "Compaction, Promotion"
+        );
+    }
+
+    #[test]
+    fn test_gc_marker_pause_fields() {
+        let mut profile = Profile::new(
+            "",
+            ReferenceTimestamp::from_millis_since_unix_epoch(0.0),
+            SamplingInterval::from_millis(1),
+        );
+        // ETW timestamps are in 100ns units.
+        let converter = TimestampConverter {
+            reference_raw: 0,
+            raw_to_ns_factor: 100,
+        };
+        let gc_type = GcType::from_raw(0);
+        let pause_kind = gc_type.pause_kind();
+        let marker = CoreClrGcDetailedMarker {
+            count: 7,
+            generation: 2,
+            reason: profile.intern_string("Induced"),
+            gc_type: profile.intern_string(&gc_type.to_string()),
+            pause_kind: profile.intern_string(pause_kind),
+            pause_ms: converter.convert_duration_ms(1_000_000, 1_250_000),
+            category: CategoryHandle::OTHER,
+        };
+        let fields = CoreClrGcDetailedMarker::schema().fields;
+        assert_eq!(fields[1].key, "generation");
+        assert_eq!(marker.number_field_value(1), 2.0);
+        assert_eq!(fields[4].key, "pauseKind");
+        assert_eq!(profile.get_string(marker.string_field_value(4)), "Blocking");
+        assert_eq!(fields[5].key, "pauseDuration");
+        assert_eq!(marker.number_field_value(5), 25.0);
+
+        assert_eq!(GcType::from_raw(1).pause_kind(), "Background");
+        assert_eq!(GcType::from_raw(2).pause_kind(), "Blocking");
+        assert_eq!(GcType::from_raw(3).pause_kind(), "Unknown");
+    }
+
+    #[test]
+    fn test_gc_suspension_marker_reason() {
+        let mut profile = Profile::new(
+            "",
+            ReferenceTimestamp::from_millis_since_unix_epoch(0.0),
+            SamplingInterval::from_millis(1),
+        );
+        let reason = GcSuspendEeReason::from_raw(5);
+        let marker = CoreClrGcSuspensionMarker {
+            name: profile.intern_string("GC Suspended Thread"),
+            reason: profile.intern_string(&reason.to_string()),
+            category: CategoryHandle::OTHER,
+        };
+        let fields = CoreClrGcSuspensionMarker::schema().fields;
+        assert_eq!(fields[0].key, "reason");
+        assert!(fields[0].searchable);
+        assert_eq!(profile.get_string(marker.string_field_value(0)), "Debugger");
+        assert_eq!(GcSuspendEeReason::from_raw(1).to_string(), "GC");
+    }
+
+    #[test]
+    fn test_is_rundown_event() {
+        assert!(is_rundown_event(
+            "Microsoft-Windows-DotNETRuntimeRundown",
+            "AssemblyDCStart"
+        ));
+        assert!(is_rundown_event(
+            "Microsoft-Windows-DotNETRuntimeRundown",
+            "DCStartComplete"
+        ));
+        assert!(is_rundown_event(
+            "Microsoft-Windows-DotNETRuntime",
+            "MethodDCEndVerbose"
+        ));
+        assert!(!is_rundown_event(
+            "Microsoft-Windows-DotNETRuntime",
+            "AssemblyLoad"
+        ));
+        assert!(!is_rundown_event(
+            "Microsoft-Windows-DotNETRuntime",
+            "MethodLoadVerbose"
+        ));
+    }
+
+    #[test]
+    fn test_coreclr_keywords_description() {
+        assert_eq!(
+            coreclr_keywords_description("Microsoft-Windows-DotNETRuntime", 0x40000001),
+            "GC, Stack"
+        );
+        assert_eq!(
+            coreclr_keywords_description("Microsoft-Windows-DotNETRuntime", 0x10_0000_0018),
+            "Loader, Jit, Compilation"
+        );
+        assert_eq!(
+            coreclr_keywords_description("Microsoft-Windows-DotNETRuntime", 0x8000_0000_0010),
+            "Jit, 0x800000000000"
+        );
+        assert_eq!(
+            coreclr_keywords_description("Microsoft-Windows-DotNETRuntime", 0),
+            "None"
+        );
+        assert_eq!(
+            coreclr_keywords_description("Microsoft-Windows-DotNETRuntimeRundown", 0x48),
+            "0x48"
+        );
+    }
+
+    #[test]
+    fn test_gc_alloc_sampling() {
+        let args_for = |gc_alloc_sampling| {
+            coreclr_xperf_args(&recording_props(CoreClrProfileProps {
+                gc_detailed_allocs: true,
+                gc_alloc_sampling,
+                ..Default::default()
+            }))
+        };
+        assert_eq!(
+            args_for(CoreClrAllocSampling::Low)[0],
+            "Microsoft-Windows-DotNETRuntime:0x2000009:4"
+        );
+        assert_eq!(
+            args_for(CoreClrAllocSampling::High)[0],
+            "Microsoft-Windows-DotNETRuntime:0x200009:4"
+        );
+        assert_eq!(
+            args_for(CoreClrAllocSampling::Both)[0],
+            "Microsoft-Windows-DotNETRuntime:0x2200009:4"
+        );
+        assert_eq!(CoreClrAllocSampling::default(), CoreClrAllocSampling::Low);
+    }
+
+    #[test]
+    fn test_extra_runtime_provider_events() {
+        let props = recording_props(CoreClrProfileProps {
+            enabled: true,
+            extra_providers: vec![
+                ("Microsoft-Windows-DotNETRuntimePrivate".to_string(), 0x1, 4),
+                ("Microsoft-Windows-DotNETRuntimeStress".to_string(), 0x1, 5),
+            ],
+            ..Default::default()
+        });
+        let args = coreclr_xperf_args(&props);
+        assert_eq!(
+            &args[2..],
+            [
+                "Microsoft-Windows-DotNETRuntimePrivate:0x1:4",
+                "Microsoft-Windows-DotNETRuntimeStress:0x1:5",
+            ]
+        );
+
+        // Events from the extra providers go to the generic event path.
+        assert!(is_coreclr_event("Microsoft-Windows-DotNETRuntime/GC/Start"));
+        assert!(is_coreclr_event(
+            "Microsoft-Windows-DotNETRuntimeRundown/Method/DCStartVerbose"
+        ));
+        assert!(!is_coreclr_event(
+            "Microsoft-Windows-DotNETRuntimePrivate/GC/Decision"
+        ));
+        assert!(!is_coreclr_event(
+            "Microsoft-Windows-DotNETRuntimeStress/StressLog/Start"
+        ));
+    }
+
+    #[test]
+    fn test_extra_providers() {
+        let props = recording_props(CoreClrProfileProps {
+            enabled: true,
+            extra_providers: vec![
+                ("Microsoft-Windows-DotNETRuntime".to_string(), 0x2000, 4),
+                ("MyCompany-MyEventSource".to_string(), 0xf, 4),
+                ("MyCompany-MyEventSource".to_string(), 0x10, 5),
+            ],
+            ..Default::default()
+        });
+        assert_eq!(
+            coreclr_xperf_args(&props),
+            vec![
+                "Microsoft-Windows-DotNETRuntime:0x2008:4",
+                "Microsoft-Windows-DotNETRuntime:0x30:5",
+                "MyCompany-MyEventSource:0x1f:5",
+            ]
+        );
+    }
+}
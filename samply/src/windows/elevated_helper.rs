@@ -40,7 +40,7 @@ impl ElevatedRecordingProps {
         Self {
             time_limit_seconds: recording_props.time_limit.map(|l| l.as_secs_f64()),
             interval_nanos: recording_props.interval.as_nanos().try_into().unwrap(),
-            coreclr: profile_creation_props.coreclr,
+            coreclr: profile_creation_props.coreclr.clone(),
             vm_hack: recording_props.vm_hack,
             is_attach: recording_mode.is_attach_mode(),
             gfx: recording_props.gfx,
@@ -13,6 +13,7 @@ use uuid::Uuid;
 
 use super::coreclr::CoreClrContext;
 use super::profile_context::ProfileContext;
+use super::winutils::stack_addresses_from_bytes;
 use crate::windows::coreclr;
 use crate::windows::profile_context::{KnownCategory, PeInfo};
 
@@ -52,6 +53,8 @@ pub fn process_etl_files(
         }
     }
 
+    core_clr_context.finish(context);
+
     log::info!(
         "Took {} seconds",
         (Instant::now() - processing_start_timestamp).as_secs_f32()
@@ -181,10 +184,7 @@ fn process_trace(
                 // not the time the ETW event was emitted (which is in the header). Use it instead.
                 let referenced_timestamp_raw: u64 = parser.parse("EventTimeStamp");
                 let stack_len = parser.buffer.len() / 8;
-                let stack_address_iter = parser
-                    .buffer
-                    .chunks_exact(8)
-                    .map(|a| u64::from_ne_bytes(a.try_into().unwrap()));
+                let stack_address_iter = stack_addresses_from_bytes(parser.buffer);
                 if is_arm64 {
                     context.handle_stack_arm64(
                         referenced_timestamp_raw,
@@ -493,8 +493,7 @@ fn process_trace(
                     text,
                 );
             }
-            dotnet_event if dotnet_event.starts_with("Microsoft-Windows-DotNETRuntime") => {
-                // Note: No "/" at end of event name, because we want DotNETRuntimeRundown as well
+            dotnet_event if coreclr::is_coreclr_event(dotnet_event) => {
                 let pid = s.process_id();
                 if !context.has_process_at_time(pid, timestamp_raw) {
                     return;
@@ -5,24 +5,27 @@ use std::sync::Arc;
 
 use debugid::DebugId;
 use fxprof_processed_profile::{
-    CategoryColor, CategoryHandle, CounterHandle, CpuDelta, Frame, FrameFlags, FrameInfo,
-    LibraryHandle, LibraryInfo, Marker, MarkerFieldFormat, MarkerFieldSchema, MarkerHandle,
-    MarkerLocation, MarkerSchema, MarkerTiming, ProcessHandle, Profile, SamplingInterval,
-    StaticSchemaMarker, StringHandle, ThreadHandle, Timestamp,
+    CategoryColor, CategoryHandle, CategoryPairHandle, CounterHandle, CpuDelta, Frame, FrameFlags,
+    FrameInfo, LibraryHandle, LibraryInfo, Marker, MarkerFieldFormat, MarkerFieldSchema,
+    MarkerHandle, MarkerLocation, MarkerSchema, MarkerTiming, ProcessHandle, Profile,
+    SamplingInterval, StaticSchemaMarker, StringHandle, ThreadHandle, Timestamp,
 };
 use shlex::Shlex;
 use wholesym::PeCodeId;
 
 use super::chrome::KeywordNames;
+use super::coreclr::RundownMethod;
 use super::winutils;
 use crate::shared::context_switch::{
     ContextSwitchHandler, OffCpuSampleGroup, ThreadContextSwitchData,
 };
 use crate::shared::included_processes::IncludedProcesses;
-use crate::shared::jit_category_manager::{JitCategoryManager, JsFrame};
+use crate::shared::jit_category_manager::{JitCategoryManager, JsFrame, ManagedCodeSubcategories};
 use crate::shared::jit_function_add_marker::JitFunctionAddMarker;
 use crate::shared::jit_function_recycler::JitFunctionRecycler;
-use crate::shared::lib_mappings::{LibMappingAdd, LibMappingInfo, LibMappingOp, LibMappingOpQueue};
+use crate::shared::lib_mappings::{
+    LibMappingAdd, LibMappingInfo, LibMappingOp, LibMappingOpQueue, LibMappingRemove,
+};
 use crate::shared::per_cpu::Cpus;
 use crate::shared::process_name::make_process_name;
 use crate::shared::process_sample_data::{ProcessSampleData, UserTimingMarker};
@@ -292,7 +295,9 @@ pub struct Process {
     pub main_thread_handle: ThreadHandle,
     pub main_thread_label_frame: FrameInfo,
     pub memory_usage: Option<MemoryUsage>,
+    pub gc_handle_counter: Option<CounterHandle>,
     pub process_id: u32,
+    pub start_timestamp_raw: u64,
     pub pid_reused_timestamp_raw: Option<u64>,
     #[allow(dead_code)]
     pub parent_id: u32,
@@ -301,11 +306,90 @@ pub struct Process {
     pub js_sources: HashMap<u64, String>,
 }
 
+/// The synthetic JIT libraries of one kind, with one library per profile process.
+/// Processes which are merged by process recycling share a profile process, and with
+/// it the library, so that their JIT functions are symbolicated the same way.
+struct ProcessJitLibraries {
+    name: &'static str,
+    default_category: CategoryPairHandle,
+    allow_recycling: bool,
+    coalesce_symbols: bool,
+    max_symbols: Option<usize>,
+    base_relative_address: u32,
+    libs: HashMap<ProcessHandle, SyntheticJitLibrary>,
+}
+
+impl ProcessJitLibraries {
+    fn new(
+        name: &'static str,
+        default_category: CategoryPairHandle,
+        props: &ProfileCreationProps,
+    ) -> Self {
+        Self {
+            name,
+            default_category,
+            allow_recycling: props.reuse_threads,
+            coalesce_symbols: props.coalesce_jit_symbols,
+            max_symbols: props.max_jit_symbols,
+            base_relative_address: props.jit_base_address,
+            libs: HashMap::new(),
+        }
+    }
+
+    /// Returns the library of the process, and creates it for the process's first
+    /// JIT function.
+    fn get_or_create(
+        &mut self,
+        process: &Process,
+        profile: &mut Profile,
+    ) -> &mut SyntheticJitLibrary {
+        self.libs.entry(process.handle).or_insert_with(|| {
+            let mut lib = SyntheticJitLibrary::new(
+                self.name.to_string(),
+                process.process_id,
+                process.start_timestamp_raw,
+                self.default_category,
+                profile,
+                self.allow_recycling,
+                self.coalesce_symbols,
+            );
+            lib.set_max_symbols(self.max_symbols);
+            lib.set_base_relative_address(self.base_relative_address);
+            lib
+        })
+    }
+
+    fn finish(self, profile: &mut Profile) {
+        for lib in self.libs.into_values() {
+            lib.finish_and_set_symbol_table(profile);
+        }
+    }
+}
+
+/// Adds the function to the JIT library and returns the op which maps it, or `None` if
+/// the library has no room for it.
+fn jit_function_add_op(
+    jit_lib: &mut SyntheticJitLibrary,
+    name: String,
+    start_avma: u64,
+    size: u32,
+    info: LibMappingInfo,
+) -> Option<LibMappingOp> {
+    let relative_address = jit_lib.add_function(name, size)?;
+    Some(LibMappingOp::Add(LibMappingAdd {
+        start_avma,
+        end_avma: start_avma + u64::from(size),
+        relative_address_at_start: relative_address,
+        info,
+    }))
+}
+
 impl Process {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: String,
         process_id: u32,
+        start_timestamp_raw: u64,
         parent_id: u32,
         handle: ProcessHandle,
         main_thread_handle: ThreadHandle,
@@ -323,7 +407,9 @@ impl Process {
             main_thread_handle,
             main_thread_label_frame,
             memory_usage: None,
+            gc_handle_counter: None,
             process_id,
+            start_timestamp_raw,
             pid_reused_timestamp_raw: None,
             parent_id,
             thread_recycler,
@@ -357,16 +443,15 @@ impl Process {
         size: u32,
         info: LibMappingInfo,
     ) {
-        let relative_address = jit_lib.add_function(name, size);
+        if let Some(op) = jit_function_add_op(jit_lib, name, start_avma, size, info) {
+            self.jit_lib_mapping_ops.push(timestamp_raw, op);
+        }
+    }
 
+    pub fn remove_jit_function(&mut self, timestamp_raw: u64, start_avma: u64) {
         self.jit_lib_mapping_ops.push(
             timestamp_raw,
-            LibMappingOp::Add(LibMappingAdd {
-                start_avma,
-                end_avma: start_avma + u64::from(size),
-                relative_address_at_start: relative_address,
-                info,
-            }),
+            LibMappingOp::Remove(LibMappingRemove { start_avma }),
         );
     }
 
@@ -386,6 +471,18 @@ impl Process {
         });
         memory_usage.counter
     }
+
+    pub fn get_gc_handle_counter(&mut self, profile: &mut Profile) -> CounterHandle {
+        let process_handle = self.handle;
+        *self.gc_handle_counter.get_or_insert_with(|| {
+            profile.add_counter(
+                process_handle,
+                "CoreCLR",
+                "GC Handles",
+                "Number of live CoreCLR GC handles",
+            )
+        })
+    }
 }
 
 // Known profiler categories, lazy-created
@@ -399,6 +496,11 @@ pub enum KnownCategory {
     CoreClrR2r,
     CoreClrJit,
     CoreClrGc,
+    CoreClrGcAlloc,
+    CoreClrGcPause,
+    CoreClrGcFinalizer,
+    CoreClrGcInduced,
+    CoreClrLoader,
     Unknown,
 }
 
@@ -418,6 +520,11 @@ impl KnownCategories {
         (KnownCategory::CoreClrR2r, "CoreCLR R2R", CategoryColor::Blue),
         (KnownCategory::CoreClrJit, "CoreCLR JIT", CategoryColor::Purple),
         (KnownCategory::CoreClrGc, "CoreCLR GC", CategoryColor::Red),
+        (KnownCategory::CoreClrGcAlloc, "CoreCLR GC Allocation", CategoryColor::Brown),
+        (KnownCategory::CoreClrGcPause, "CoreCLR GC Pause", CategoryColor::Magenta),
+        (KnownCategory::CoreClrGcFinalizer, "CoreCLR GC Finalizer", CategoryColor::Green),
+        (KnownCategory::CoreClrGcInduced, "CoreCLR Induced GC", CategoryColor::Orange),
+        (KnownCategory::CoreClrLoader, "CoreCLR Loader", CategoryColor::LightBlue),
         (KnownCategory::Unknown, "Other", CategoryColor::DarkGray),
     ];
 
@@ -483,8 +590,9 @@ pub struct ProfileContext {
     known_images: HashMap<(String, u32, u32), (LibraryHandle, KnownCategory)>,
 
     js_category_manager: JitCategoryManager,
-    js_jit_lib: SyntheticJitLibrary,
-    coreclr_jit_lib: SyntheticJitLibrary,
+    js_jit_libs: ProcessJitLibraries,
+    coreclr_jit_libs: ProcessJitLibraries,
+    coreclr_jit_subcategories: ManagedCodeSubcategories,
 
     context_switch_handler: ContextSwitchHandler,
 
@@ -550,19 +658,16 @@ impl ProfileContext {
         let mut categories = KnownCategories::new();
         let mut js_category_manager = JitCategoryManager::new();
         let default_js_jit_category = js_category_manager.default_category(&mut profile);
-        let allow_jit_function_recycling = profile_creation_props.reuse_threads;
-        let js_jit_lib = SyntheticJitLibrary::new(
-            "JS JIT".to_string(),
+        let js_jit_libs = ProcessJitLibraries::new(
+            "JS JIT",
             default_js_jit_category.into(),
-            &mut profile,
-            allow_jit_function_recycling,
+            &profile_creation_props,
         );
         let coreclr_jit_category = categories.get(KnownCategory::CoreClrJit, &mut profile);
-        let coreclr_jit_lib = SyntheticJitLibrary::new(
-            "CoreCLR JIT".to_string(),
+        let coreclr_jit_libs = ProcessJitLibraries::new(
+            "CoreCLR JIT",
             coreclr_jit_category.into(),
-            &mut profile,
-            allow_jit_function_recycling,
+            &profile_creation_props,
         );
 
         let cpus = if profile_creation_props.create_per_cpu_threads {
@@ -587,8 +692,9 @@ impl ProfileContext {
             categories,
             known_images: HashMap::new(),
             js_category_manager,
-            js_jit_lib,
-            coreclr_jit_lib,
+            js_jit_libs,
+            coreclr_jit_libs,
+            coreclr_jit_subcategories: ManagedCodeSubcategories::new(coreclr_jit_category),
             context_switch_handler: ContextSwitchHandler::new(122100), // hardcoded, but replaced once TraceStart is received
             device_mappings: winutils::get_dos_device_mappings(),
             kernel_min,
@@ -663,6 +769,11 @@ impl ProfileContext {
         }
     }
 
+    pub fn duration_ms(&self, start_timestamp_raw: u64, end_timestamp_raw: u64) -> f64 {
+        self.timestamp_converter
+            .convert_duration_ms(start_timestamp_raw, end_timestamp_raw)
+    }
+
     pub fn known_category(&mut self, known_category: KnownCategory) -> CategoryHandle {
         self.categories.get(known_category, &mut self.profile)
     }
@@ -782,6 +893,7 @@ impl ProfileContext {
         let process = Process::new(
             name,
             pid,
+            timestamp_raw,
             parent_pid,
             process_handle,
             main_thread_handle,
@@ -852,6 +964,7 @@ impl ProfileContext {
         let process = Process::new(
             name,
             pid,
+            timestamp_raw,
             parent_pid,
             process_handle,
             main_thread_handle,
@@ -1075,6 +1188,22 @@ impl ProfileContext {
         thread.name = Some(name);
     }
 
+    /// Like `handle_thread_set_name`, but doesn't replace a name that the thread
+    /// already has. Used for names which we infer from the events on a thread.
+    pub fn handle_thread_set_default_name(
+        &mut self,
+        timestamp_raw: u64,
+        pid: u32,
+        tid: u32,
+        name: &str,
+    ) {
+        match self.threads.get_by_tid(tid) {
+            Some(thread) if thread.name.is_none() => {}
+            _ => return,
+        }
+        self.handle_thread_set_name(timestamp_raw, pid, tid, name.to_owned());
+    }
+
     pub fn handle_thread_end(&mut self, timestamp_raw: u64, pid: u32, tid: u32) {
         let Some(thread) = self.threads.get_by_tid(tid) else {
             return;
@@ -1463,6 +1592,22 @@ impl ProfileContext {
         // TODO: Consider adding a marker here
     }
 
+    pub fn handle_coreclr_gc_handle_count_change(
+        &mut self,
+        timestamp_raw: u64,
+        pid: u32,
+        delta: f64,
+    ) {
+        let Some(process) = self.processes.get_by_pid(pid) else {
+            return;
+        };
+
+        let timestamp = self.timestamp_converter.convert_time(timestamp_raw);
+        let profile = &mut self.profile;
+        let counter = process.get_gc_handle_counter(profile);
+        profile.add_counter_sample(counter, timestamp, delta, 1);
+    }
+
     fn lib_handle_and_category_for_image(
         &mut self,
         device_path: String,
@@ -1766,7 +1911,7 @@ impl ProfileContext {
                 if line != 0 {
                     write!(&mut method_name, ":{line}:{column}").unwrap();
                 }
-                let category = self.js_jit_lib.default_category();
+                let category = self.js_jit_libs.default_category;
                 let js_frame = Some(JsFrame::NativeFrameIsJs);
                 (category, js_frame)
             }
@@ -1776,7 +1921,7 @@ impl ProfileContext {
                 .classify_jit_symbol(&method_name, &mut self.profile)
         };
 
-        let lib = &mut self.js_jit_lib;
+        let lib = self.js_jit_libs.get_or_create(process, &mut self.profile);
         let info = LibMappingInfo::new_jit_function(lib.lib_handle(), category, js_frame);
 
         let name_handle = self.profile.intern_string(&method_name);
@@ -1802,6 +1947,7 @@ impl ProfileContext {
         timestamp_raw: u64,
         pid: u32,
         method_name: String,
+        method_namespace: &str,
         method_start_address: u64,
         method_size: u32,
     ) {
@@ -1809,8 +1955,12 @@ impl ProfileContext {
             return;
         };
 
-        let lib = &mut self.coreclr_jit_lib;
-        let info = LibMappingInfo::new_jit_function(lib.lib_handle(), lib.default_category(), None);
+        let category = self
+            .coreclr_jit_subcategories
+            .classify(method_namespace, &mut self.profile);
+        let profile = &mut self.profile;
+        let lib = self.coreclr_jit_libs.get_or_create(process, profile);
+        let info = LibMappingInfo::new_jit_function(lib.lib_handle(), category, None);
 
         process.add_jit_function(
             timestamp_raw,
@@ -1822,6 +1972,56 @@ impl ProfileContext {
         );
     }
 
+    /// Adds the methods listed by a completed end rundown. These methods existed before
+    /// the capture started, so their symbols are valid from the capture start.
+    pub fn handle_coreclr_rundown_complete(
+        &mut self,
+        timestamp_raw: u64,
+        pid: u32,
+        methods: Vec<RundownMethod>,
+    ) {
+        let Some(process) = self.processes.get_by_pid_and_timestamp(pid, timestamp_raw) else {
+            return;
+        };
+
+        let capture_start_raw = self.timestamp_converter.reference_raw;
+        let profile = &mut self.profile;
+        let lib = self.coreclr_jit_libs.get_or_create(process, profile);
+        let mut ops = Vec::with_capacity(methods.len());
+        for method in methods {
+            let category = self
+                .coreclr_jit_subcategories
+                .classify(&method.namespace, &mut self.profile);
+            let info = LibMappingInfo::new_jit_function(lib.lib_handle(), category, None);
+            ops.extend(jit_function_add_op(
+                lib,
+                method.name,
+                method.start_address,
+                method.size,
+                info,
+            ));
+        }
+        // The capture start is older than the events we've already seen, so the ops
+        // are merged into the queue.
+        let queue = &mut process.jit_lib_mapping_ops;
+        queue.insert_all(capture_start_raw, ops);
+    }
+
+    /// Ends the JIT mapping for an unloaded CoreCLR method. Nothing happens if no
+    /// method was loaded at this address.
+    pub fn handle_coreclr_method_unload(
+        &mut self,
+        timestamp_raw: u64,
+        pid: u32,
+        method_start_address: u64,
+    ) {
+        let Some(process) = self.processes.get_by_pid_and_timestamp(pid, timestamp_raw) else {
+            return;
+        };
+
+        process.remove_jit_function(timestamp_raw, method_start_address);
+    }
+
     pub fn handle_freeform_marker_start(
         &mut self,
         timestamp_raw: u64,
@@ -2051,10 +2251,8 @@ impl ProfileContext {
         // samply does on Linux and macOS, where the queued samples also want to respect JIT function names from
         // a /tmp/perf-1234.map file, and this file may not exist until the profiled process finishes.)
         let mut stack_frame_scratch_buf = Vec::new();
-        self.js_jit_lib
-            .finish_and_set_symbol_table(&mut self.profile);
-        self.coreclr_jit_lib
-            .finish_and_set_symbol_table(&mut self.profile);
+        self.js_jit_libs.finish(&mut self.profile);
+        self.coreclr_jit_libs.finish(&mut self.profile);
         let process_sample_datas = self.processes.finish();
 
         let user_category = self.categories.get(KnownCategory::User, &mut self.profile);
@@ -2274,3 +2472,63 @@ fn extract_filename(path: &str) -> &str {
         None => path,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use fxprof_processed_profile::ReferenceTimestamp;
+
+    use super::*;
+    use crate::shared::lib_mappings::LibMappingsHierarchy;
+
+    fn profile_context() -> ProfileContext {
+        let profile = Profile::new(
+            "test",
+            ReferenceTimestamp::from_millis_since_unix_epoch(0.0),
+            SamplingInterval::from_millis(1),
+        );
+        let props = ProfileCreationProps {
+            profile_name: None,
+            fallback_profile_name: "test".to_string(),
+            main_thread_only: false,
+            reuse_threads: false,
+            fold_recursive_prefix: false,
+            coalesce_jit_symbols: false,
+            max_jit_symbols: None,
+            jit_base_address: 0,
+            unlink_aux_files: false,
+            create_per_cpu_threads: false,
+            arg_count_to_include_in_process_name: 0,
+            override_arch: None,
+            unstable_presymbolicate: false,
+            presymbolicate_timeout: std::time::Duration::from_secs(60),
+            coreclr: Default::default(),
+            unknown_event_markers: false,
+            coreclr_thread_filter: Default::default(),
+            time_range: None,
+        };
+        ProfileContext::new(profile, "x86_64", None, props)
+    }
+
+    #[test]
+    fn test_coreclr_method_unload() {
+        let mut context = profile_context();
+        let pid = 100;
+        context.handle_process_start(0, pid, 1, "app.exe".to_string(), "app.exe".to_string());
+        context.handle_coreclr_method_load(10, pid, "M".to_string(), "N", 0x1000, 0x100);
+        // Unloading an address that was never loaded does nothing.
+        context.handle_coreclr_method_unload(15, pid, 0x5000);
+        context.handle_coreclr_method_unload(20, pid, 0x1000);
+        // Nor does unloading in a process we don't know.
+        context.handle_coreclr_method_unload(20, 200, 0x1000);
+
+        let process = context.processes.get_by_pid(pid).unwrap();
+        let ops = std::mem::take(&mut process.jit_lib_mapping_ops);
+        let mut mappings = LibMappingsHierarchy::new(LibMappingOpQueue::default());
+        mappings.add_jitdump_lib_mappings_ops(ops);
+        mappings.process_ops(15);
+        let (relative_address, _info) = mappings.convert_address(0x1010).unwrap();
+        assert_eq!(relative_address, 0x10);
+        mappings.process_ops(20);
+        assert!(mappings.convert_address(0x1010).is_none());
+    }
+}
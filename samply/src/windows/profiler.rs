@@ -4,6 +4,7 @@ use std::process::ExitStatus;
 
 use fxprof_processed_profile::{Profile, ReferenceTimestamp, SamplingInterval};
 
+use super::coreclr::{coreclr_overhead_estimate, CoreClrOverhead};
 use super::etw_gecko;
 use super::profile_context::ProfileContext;
 use crate::server::{start_server_main, ServerProps};
@@ -56,6 +57,14 @@ pub fn start_recording(
         SamplingInterval::from_nanos(1000000), // will be replaced with correct interval from file later
     );
 
+    let (overhead, reasons) = coreclr_overhead_estimate(&profile_creation_props.coreclr);
+    if overhead >= CoreClrOverhead::High {
+        eprintln!("Warning: The CoreCLR flags have {overhead} recording overhead:");
+        for (_, reason) in reasons {
+            eprintln!("  - {reason}");
+        }
+    }
+
     // Start xperf.
     let mut elevated_helper = ElevatedHelperSession::new(recording_props.output_file.clone())
         .unwrap_or_else(|e| panic!("Couldn't start elevated helper process: {e:?}"));
@@ -142,6 +151,7 @@ pub fn start_recording(
         .unwrap_or(get_native_arch().to_string());
 
     let unstable_presymbolicate = profile_creation_props.unstable_presymbolicate;
+    let presymbolicate_timeout = profile_creation_props.presymbolicate_timeout;
     let mut context =
         ProfileContext::new(profile, &arch, included_processes, profile_creation_props);
     let extra_etls = match &user_output_file {
@@ -184,6 +194,7 @@ pub fn start_recording(
         crate::shared::symbol_precog::presymbolicate(
             &profile,
             &output_file.with_extension("syms.json"),
+            presymbolicate_timeout,
         );
     }
 
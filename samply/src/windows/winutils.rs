@@ -105,6 +105,24 @@ pub fn from_zero_terminated_wstr(wstr: &[u16]) -> String {
     path_os_str.to_string_lossy().into()
 }
 
+/// Reads the 64-bit addresses from the raw bytes of a stack walk event.
+///
+/// The stack is expected to be a whole number of addresses. If it isn't, the
+/// trailing bytes are ignored; we warn about them unless they're zero padding,
+/// because that could mean that we're dropping the outermost frame.
+pub fn stack_addresses_from_bytes(bytes: &[u8]) -> impl Iterator<Item = u64> + '_ {
+    let chunks = bytes.chunks_exact(8);
+    let remainder = chunks.remainder();
+    if remainder.iter().any(|&b| b != 0) {
+        log::warn!(
+            "Ignoring {} trailing bytes after {} stack addresses",
+            remainder.len(),
+            bytes.len() / 8
+        );
+    }
+    chunks.map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+}
+
 // This is a hack to convert a path like \Device\HarddiskVolume4\Windows\System32\ntoskrnl.exe
 // into C:\Windows\System32\ntoskrnl.exe . This turns out to be rocket science, and the Rtl
 // method that's supposed to do it (RtlNtPathNameToDosPathName) doesn't seem to actually work.
@@ -207,3 +225,24 @@ pub fn iter_kernel_drivers() -> impl Iterator<Item = (String, u64, u64)> {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stack_addresses_from_bytes() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0x7ff6_1234_5678u64.to_le_bytes());
+        bytes.extend_from_slice(&0x7ffa_0000_1000u64.to_le_bytes());
+        let addresses: Vec<u64> = stack_addresses_from_bytes(&bytes).collect();
+        assert_eq!(addresses, vec![0x7ff6_1234_5678, 0x7ffa_0000_1000]);
+
+        // Trailing bytes which don't make up a full address are not returned as a frame.
+        bytes.extend_from_slice(&[0xab, 0xcd, 0x00, 0x00]);
+        let addresses: Vec<u64> = stack_addresses_from_bytes(&bytes).collect();
+        assert_eq!(addresses, vec![0x7ff6_1234_5678, 0x7ffa_0000_1000]);
+
+        assert_eq!(stack_addresses_from_bytes(&[0; 3]).count(), 0);
+    }
+}